@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 
+use chrono::TimeDelta;
 use clap::{Parser, Subcommand};
+use prj_core::storage::StorageBackend;
 
 #[derive(Parser)]
 #[command(name = "prj", about = "Local project manager", version)]
@@ -24,9 +26,30 @@ pub enum Command {
     Scan {
         /// Directory to scan
         dir: PathBuf,
-        /// Maximum directory depth to scan
-        #[arg(long, default_value = "3")]
-        depth: usize,
+        /// Maximum directory depth to scan (defaults to `scan_depth` in the config)
+        #[arg(long)]
+        depth: Option<usize>,
+        /// Skip directories matching this gitignore-style glob (repeatable)
+        #[arg(long)]
+        exclude: Vec<String>,
+        /// Descend into this hidden directory (repeatable)
+        #[arg(long)]
+        include_hidden: Vec<String>,
+        /// Follow symbolic links
+        #[arg(long)]
+        follow_symlinks: bool,
+        /// Don't honour .gitignore files (.prjignore still applies)
+        #[arg(long)]
+        no_gitignore: bool,
+        /// Tag every newly registered project
+        #[arg(long)]
+        tag: Option<String>,
+        /// Unregister projects under DIR whose directories no longer exist
+        #[arg(long)]
+        prune: bool,
+        /// Only show what would be registered or pruned
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Git clone and auto-register
@@ -38,27 +61,82 @@ pub enum Command {
 
     /// Unregister a project (no file deletion)
     Remove {
-        /// Project name to remove
+        /// Project name, path, or unique path suffix
         project: String,
     },
 
+    /// Rename a registered project
+    Rename {
+        /// Project name, path, or unique path suffix
+        project: String,
+        /// New display name
+        new_name: String,
+    },
+
+    /// Point a project at a new location, optionally moving its directory
+    Mv {
+        /// Project name, path, or unique path suffix
+        project: String,
+        /// New project directory
+        new_path: PathBuf,
+        /// Also move the directory on disk (otherwise it must already be there)
+        #[arg(long = "move")]
+        move_dir: bool,
+    },
+
+    /// Re-detect VCS, build systems and artifact directories
+    Refresh {
+        /// Project name, path, or unique path suffix (omit with --all for all projects)
+        project: Option<String>,
+        /// Refresh all projects
+        #[arg(long)]
+        all: bool,
+    },
+
     /// List registered projects
     List {
         /// Plain text output (no TUI)
         #[arg(long)]
         plain: bool,
-        /// Filter by tag
+        /// Filter by tag (also matches nested tags like `tag/sub`)
         #[arg(long)]
         tag: Option<String>,
+        /// Filter by primary language (e.g. `rust`, case-insensitive)
+        #[arg(long)]
+        lang: Option<String>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Show project statistics
     Stats {
-        /// Specific project name (omit for overview)
+        /// Project name, path, or unique path suffix (omit for overview)
         project: Option<String>,
+        /// Limit the overview to projects with this tag (or nested tags)
+        #[arg(long, conflicts_with = "project")]
+        tag: Option<String>,
+        /// Limit the overview to projects with this primary language
+        #[arg(long, conflicts_with = "project")]
+        lang: Option<String>,
         /// Output as JSON
         #[arg(long)]
         json: bool,
+        /// Recompute lines of code and disk usage instead of using the cache
+        #[arg(long)]
+        refresh: bool,
+        /// Append a snapshot of the collected stats to the stats history
+        #[arg(long, conflicts_with = "history")]
+        record: bool,
+        /// Show how recorded stats changed over time instead of current stats
+        #[arg(long)]
+        history: bool,
+        /// With --history, only use snapshots newer than this (e.g. `90d`, `12w`, `1y`)
+        #[arg(long, requires = "history", value_parser = parse_age)]
+        since: Option<TimeDelta>,
+        /// With --history, output one CSV row per snapshot
+        #[arg(long, requires = "history", conflicts_with = "json")]
+        csv: bool,
     },
 
     /// Output shell init script
@@ -72,7 +150,7 @@ pub enum Command {
 
     /// Add tags to a project
     Tag {
-        /// Project name
+        /// Project name, path, or unique path suffix
         project: String,
         /// Tags to add
         tags: Vec<String>,
@@ -80,14 +158,38 @@ pub enum Command {
 
     /// Remove tags from a project
     Untag {
-        /// Project name
+        /// Project name, path, or unique path suffix
         project: String,
         /// Tags to remove
         tags: Vec<String>,
     },
 
+    /// List every tag with project counts, or rename/delete a tag everywhere
+    Tags {
+        #[command(subcommand)]
+        action: Option<TagsAction>,
+    },
+
+    /// Manage a project's description and custom metadata
+    Meta {
+        #[command(subcommand)]
+        action: MetaAction,
+    },
+
+    /// Manage git worktrees of a project
+    Worktree {
+        #[command(subcommand)]
+        action: WorktreeAction,
+    },
+
     /// Quick git status dashboard across all projects
     Status {
+        /// Only show projects with this tag (or nested tags)
+        #[arg(long)]
+        tag: Option<String>,
+        /// Also show commit activity: last commit, recent commits and authors
+        #[arg(long)]
+        long: bool,
         /// Output as JSON
         #[arg(long)]
         json: bool,
@@ -100,10 +202,52 @@ pub enum Command {
         dry_run: bool,
     },
 
+    /// List projects with no recent activity, optionally cleaning, archiving or removing them
+    Stale {
+        /// Only report projects inactive for longer than this (e.g. `180d`, `26w`, `1y`)
+        #[arg(long, value_parser = parse_age, default_value = "180d")]
+        older_than: TimeDelta,
+        /// Only consider projects with this tag (or nested tags)
+        #[arg(long)]
+        tag: Option<String>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        /// Delete the build artifacts of every stale project
+        #[arg(long, conflicts_with_all = ["archive", "remove", "json"])]
+        clean: bool,
+        /// Tag every stale project `archived`
+        #[arg(long, conflicts_with_all = ["remove", "json"])]
+        archive: bool,
+        /// Unregister every stale project (files are kept)
+        #[arg(long, conflicts_with = "json")]
+        remove: bool,
+    },
+
+    /// Check registered projects for problems
+    Doctor {
+        /// Only check that pinned toolchain versions are installed
+        #[arg(long)]
+        toolchains: bool,
+    },
+
+    /// Revert the most recent change to the project database
+    Undo,
+
+    /// Show recent changes to the project database, newest first
+    History {
+        /// Maximum number of entries to show
+        #[arg(long, short = 'n', default_value_t = 20)]
+        limit: usize,
+    },
+
     /// Delete artifact directories (target, node_modules, etc.)
     Clean {
-        /// Project name (omit with --all for all projects)
+        /// Project name, path, or unique path suffix (omit with --all for all projects)
         project: Option<String>,
+        /// Clean projects with this tag (or nested tags)
+        #[arg(long)]
+        tag: Option<String>,
         /// Clean all projects
         #[arg(long)]
         all: bool,
@@ -116,12 +260,18 @@ pub enum Command {
     Run {
         /// Command to execute
         cmd: String,
-        /// Target specific project
+        /// Target specific project (name, path, or unique path suffix)
         #[arg(long)]
         project: Option<String>,
-        /// Filter by tag
+        /// Run in each workspace member of --project instead of the project itself
+        #[arg(long, requires = "project")]
+        members: bool,
+        /// Filter by tag (also matches nested tags like `tag/sub`)
         #[arg(long)]
         tag: Option<String>,
+        /// Filter by primary language (e.g. `rust`, case-insensitive)
+        #[arg(long)]
+        lang: Option<String>,
         /// Run in all projects
         #[arg(long)]
         all: bool,
@@ -135,6 +285,15 @@ pub enum Command {
         /// Base directory for relative paths
         #[arg(long)]
         base_dir: Option<PathBuf>,
+        /// Only export projects with this tag (or nested tags)
+        #[arg(long)]
+        tag: Option<String>,
+    },
+
+    /// Manage the project database itself
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
     },
 
     /// Import and clone projects from a manifest
@@ -146,3 +305,97 @@ pub enum Command {
         base_dir: Option<PathBuf>,
     },
 }
+
+#[derive(Subcommand)]
+pub enum TagsAction {
+    /// Rename a tag on every project, including tags nested below it
+    Rename {
+        /// Current tag
+        old: String,
+        /// New tag
+        new: String,
+    },
+
+    /// Remove a tag, and tags nested below it, from every project
+    Delete {
+        /// Tag to remove
+        tag: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum WorktreeAction {
+    /// Create a worktree checking out a branch and register it
+    Add {
+        /// Project name, path, or unique path suffix
+        project: String,
+        /// Branch to check out (created from HEAD if it doesn't exist)
+        branch: String,
+        /// Where to create the worktree (defaults to `<repo>-<branch>` next to the repository)
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MetaAction {
+    /// Set a field (the `description` key sets the description)
+    Set {
+        /// Project name, path, or unique path suffix
+        project: String,
+        /// Field name, e.g. `owner` or `description`
+        key: String,
+        /// Field value
+        value: String,
+    },
+
+    /// Remove a field
+    Unset {
+        /// Project name, path, or unique path suffix
+        project: String,
+        /// Field name
+        key: String,
+    },
+
+    /// Print a field, or every field if no key is given
+    Get {
+        /// Project name, path, or unique path suffix
+        project: String,
+        /// Field name
+        key: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DbAction {
+    /// Copy the database into another storage backend
+    Migrate {
+        /// Target backend (toml, sqlite)
+        #[arg(long)]
+        to: StorageBackend,
+        /// Where to write the new database (defaults to the backend's standard location)
+        #[arg(long)]
+        path: Option<PathBuf>,
+        /// Overwrite a target database that already contains projects
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Parse an age such as `36h`, `90d`, `12w` or `1y`.
+pub fn parse_age(s: &str) -> Result<TimeDelta, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (count, unit) = s.split_at(split);
+    let count: i64 = count
+        .parse()
+        .map_err(|_| format!("invalid age '{s}' (expected e.g. 90d)"))?;
+    let age = match unit {
+        "h" => TimeDelta::try_hours(count),
+        "d" => TimeDelta::try_days(count),
+        "w" => count.checked_mul(7).and_then(TimeDelta::try_days),
+        "y" => count.checked_mul(365).and_then(TimeDelta::try_days),
+        _ => return Err(format!("invalid age unit in '{s}' (use h, d, w or y)")),
+    };
+    age.filter(|age| chrono::Utc::now().checked_sub_signed(*age).is_some())
+        .ok_or_else(|| format!("age '{s}' is too large"))
+}
@@ -3,18 +3,31 @@ pub mod args;
 use std::io::{self, IsTerminal, Write};
 use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use bytesize::ByteSize;
+use color_eyre::eyre::{bail, eyre};
 
 use prj_core::clean;
 use prj_core::config::Config;
 use prj_core::detect;
 use prj_core::error::PrjError;
+use prj_core::journal::{Journal, MovedDir, Operation};
 use prj_core::manifest;
-use prj_core::project::ProjectDatabase;
+use prj_core::project::{DESCRIPTION_KEY, ProjectDatabase};
+use prj_core::scan::{self, ScanOptions};
+use prj_core::stale;
 use prj_core::stats;
+use prj_core::stats_cache::StatsCache;
+use prj_core::stats_history::{Snapshot, StatsHistory};
+use prj_core::storage::{self, StorageBackend};
+use prj_core::tags;
+use prj_core::toolchain;
+use prj_core::workspace;
+use prj_core::worktree;
 
-use self::args::{Cli, Command};
+use self::args::{Cli, Command, DbAction, MetaAction, TagsAction, WorktreeAction};
 
 pub fn run(cli: Cli) -> color_eyre::Result<()> {
     let config = Config::load()?;
@@ -22,33 +35,147 @@ pub fn run(cli: Cli) -> color_eyre::Result<()> {
     match cli.command {
         None => cmd_picker(&config)?,
         Some(Command::Add { path, name }) => cmd_add(&config, path, name.as_deref())?,
-        Some(Command::Scan { dir, depth }) => cmd_scan(&config, &dir, depth)?,
+        Some(Command::Scan {
+            dir,
+            depth,
+            exclude,
+            include_hidden,
+            follow_symlinks,
+            no_gitignore,
+            tag,
+            prune,
+            dry_run,
+        }) => {
+            let mut options = config.scan_options();
+            if let Some(depth) = depth {
+                options.max_depth = depth;
+            }
+            options.exclude.extend(exclude);
+            options.include_hidden.extend(include_hidden);
+            options.follow_symlinks |= follow_symlinks;
+            options.respect_gitignore &= !no_gitignore;
+            let cmd = ScanCommand {
+                tag: tag.as_deref(),
+                prune,
+                dry_run,
+            };
+            cmd_scan(&config, &dir, &options, cmd)?
+        }
         Some(Command::New { git }) => cmd_new(&config, &git)?,
         Some(Command::Remove { project }) => cmd_remove(&config, &project)?,
-        Some(Command::List { plain, tag }) => cmd_list(&config, plain, tag.as_deref())?,
-        Some(Command::Stats { project, json }) => cmd_stats(&config, project.as_deref(), json)?,
+        Some(Command::Rename { project, new_name }) => cmd_rename(&config, &project, &new_name)?,
+        Some(Command::Mv {
+            project,
+            new_path,
+            move_dir,
+        }) => cmd_mv(&config, &project, &new_path, move_dir)?,
+        Some(Command::Refresh { project, all }) => cmd_refresh(&config, project.as_deref(), all)?,
+        Some(Command::List {
+            plain,
+            tag,
+            lang,
+            json,
+        }) => cmd_list(&config, plain, tag.as_deref(), lang.as_deref(), json)?,
+        Some(Command::Stats {
+            project,
+            tag,
+            lang,
+            json,
+            refresh,
+            record,
+            history,
+            since,
+            csv,
+        }) => {
+            if history {
+                cmd_stats_history(
+                    &config,
+                    project.as_deref(),
+                    tag.as_deref(),
+                    lang.as_deref(),
+                    since,
+                    json,
+                    csv,
+                )?
+            } else {
+                cmd_stats(
+                    &config,
+                    project.as_deref(),
+                    tag.as_deref(),
+                    lang.as_deref(),
+                    json,
+                    refresh,
+                    record,
+                )?
+            }
+        }
         Some(Command::Init { shell, cmd }) => cmd_init(&shell, &cmd)?,
         Some(Command::Tag { project, tags }) => cmd_tag(&config, &project, &tags)?,
         Some(Command::Untag { project, tags }) => cmd_untag(&config, &project, &tags)?,
-        Some(Command::Status { json }) => cmd_status(&config, json)?,
+        Some(Command::Tags { action }) => cmd_tags(&config, action)?,
+        Some(Command::Meta { action }) => cmd_meta(&config, action)?,
+        Some(Command::Worktree { action }) => cmd_worktree(&config, action)?,
+        Some(Command::Status { tag, long, json }) => {
+            cmd_status(&config, tag.as_deref(), long, json)?
+        }
         Some(Command::Gc { dry_run }) => cmd_gc(&config, dry_run)?,
+        Some(Command::Stale {
+            older_than,
+            tag,
+            json,
+            clean,
+            archive,
+            remove,
+        }) => cmd_stale(
+            &config,
+            older_than,
+            tag.as_deref(),
+            json,
+            clean,
+            archive,
+            remove,
+        )?,
+        Some(Command::Doctor { toolchains }) => cmd_doctor(&config, toolchains)?,
+        Some(Command::Undo) => cmd_undo(&config)?,
+        Some(Command::History { limit }) => cmd_history(&config, limit)?,
         Some(Command::Clean {
             project,
+            tag,
             all,
             dry_run,
-        }) => cmd_clean(&config, project.as_deref(), all, dry_run)?,
+        }) => cmd_clean(&config, project.as_deref(), tag.as_deref(), all, dry_run)?,
         Some(Command::Run {
             cmd,
             project,
+            members,
             tag,
+            lang,
             all,
-        }) => cmd_run(&config, &cmd, project.as_deref(), tag.as_deref(), all)?,
-        Some(Command::Export { output, base_dir }) => {
-            cmd_export(&config, output.as_deref(), base_dir.as_deref())?
-        }
+        }) => cmd_run(
+            &config,
+            &cmd,
+            project.as_deref(),
+            members,
+            tag.as_deref(),
+            lang.as_deref(),
+            all,
+        )?,
+        Some(Command::Export {
+            output,
+            base_dir,
+            tag,
+        }) => cmd_export(
+            &config,
+            output.as_deref(),
+            base_dir.as_deref(),
+            tag.as_deref(),
+        )?,
         Some(Command::Import { file, base_dir }) => {
             cmd_import(&config, &file, base_dir.as_deref())?
         }
+        Some(Command::Db {
+            action: DbAction::Migrate { to, path, force },
+        }) => cmd_db_migrate(&config, to, path.as_deref(), force)?,
     }
 
     Ok(())
@@ -56,18 +183,34 @@ pub fn run(cli: Cli) -> color_eyre::Result<()> {
 
 fn cmd_add(config: &Config, path: Option<PathBuf>, name: Option<&str>) -> color_eyre::Result<()> {
     let path = path.unwrap_or_else(|| std::env::current_dir().expect("could not get cwd"));
-    let mut db = ProjectDatabase::load(config)?;
-    let project = db.register(&path, name)?;
+    let mut db = ProjectDatabase::lock(config)?;
+    let project = db.register(&path, name, &config.build_systems)?;
     eprintln!(
         "Added project: {} ({})",
         project.name,
         project.path.display()
     );
-    db.save(config)?;
+    let name = project.name.clone();
+    for member in db.register_members(&name, &config.build_systems)? {
+        eprintln!("  + {member}");
+    }
+    db.commit(Operation::Add, format!("added {name}"))?;
     Ok(())
 }
 
-fn cmd_scan(config: &Config, dir: &Path, depth: usize) -> color_eyre::Result<()> {
+/// Options for `prj scan` beyond how the directory tree is walked.
+struct ScanCommand<'a> {
+    tag: Option<&'a str>,
+    prune: bool,
+    dry_run: bool,
+}
+
+fn cmd_scan(
+    config: &Config,
+    dir: &Path,
+    options: &ScanOptions,
+    cmd: ScanCommand,
+) -> color_eyre::Result<()> {
     let dir = dir
         .canonicalize()
         .map_err(|_| PrjError::PathNotFound(dir.to_path_buf()))?;
@@ -75,16 +218,35 @@ fn cmd_scan(config: &Config, dir: &Path, depth: usize) -> color_eyre::Result<()>
     if !dir.is_dir() {
         return Err(PrjError::NotADirectory(dir).into());
     }
+    let tag = cmd.tag.map(tags::normalize).transpose()?;
 
-    let mut db = ProjectDatabase::load(config)?;
-    let found = detect::scan_projects(&dir, depth);
+    let progress = ScanProgressLine::new();
+    let found = scan::scan_projects(&dir, options, &config.build_systems, &|p| {
+        progress.update(p)
+    })?;
+    progress.finish();
+
+    if cmd.dry_run {
+        return scan_dry_run(config, &dir, &found, cmd.prune);
+    }
 
-    let mut added = 0;
+    let mut db = ProjectDatabase::lock(config)?;
+
+    let mut added = Vec::new();
     for path in &found {
-        match db.register(path, None) {
+        match db.register_disambiguated(path, None, &config.build_systems) {
             Ok(p) => {
-                eprintln!("  + {}", p.name);
-                added += 1;
+                let name = p.name.clone();
+                match db.register_members(&name, &config.build_systems) {
+                    Ok(members) => {
+                        added.push(name);
+                        added.extend(members);
+                    }
+                    Err(e) => {
+                        eprintln!("  ! {name} members: {e}");
+                        added.push(name);
+                    }
+                }
             }
             Err(PrjError::ProjectAlreadyExists(_)) => {}
             Err(e) => {
@@ -92,16 +254,136 @@ fn cmd_scan(config: &Config, dir: &Path, depth: usize) -> color_eyre::Result<()>
             }
         }
     }
+    for name in &added {
+        if let Some(tag) = &tag {
+            db.add_tags(name, std::slice::from_ref(tag))?;
+        }
+        eprintln!("  + {name}");
+    }
+
+    let pruned = if cmd.prune {
+        db.remove_orphaned_under(&dir)
+    } else {
+        Vec::new()
+    };
+    for p in &pruned {
+        eprintln!("  - {} ({})", p.name, p.path.display());
+    }
 
-    db.save(config)?;
+    let mut summary = format!("added {} projects from {}", added.len(), dir.display());
+    if !pruned.is_empty() {
+        summary.push_str(&format!(", pruned {}", pruned.len()));
+    }
+    db.commit(Operation::Scan, summary)?;
     eprintln!(
-        "Scan complete: found {} projects, added {} new",
+        "Scan complete: found {} projects, added {} new, removed {}",
         found.len(),
-        added
+        added.len(),
+        pruned.len()
     );
     Ok(())
 }
 
+/// Print what `prj scan` would register and prune. Works from an unlocked
+/// read of the database and skips detection, so a preview neither blocks
+/// other commands nor pays for a full registration.
+fn scan_dry_run(
+    config: &Config,
+    dir: &Path,
+    found: &[PathBuf],
+    prune: bool,
+) -> color_eyre::Result<()> {
+    let db = ProjectDatabase::load(config)?;
+    let registered = |path: &Path| db.projects.iter().any(|p| p.path == path);
+
+    let mut would_add: Vec<PathBuf> = Vec::new();
+    for path in found {
+        let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if registered(&path) || would_add.contains(&path) {
+            continue;
+        }
+        let members = workspace::workspace_members(&path, &config.build_systems);
+        would_add.push(path);
+        for member in members {
+            if !registered(&member) && !would_add.contains(&member) {
+                would_add.push(member);
+            }
+        }
+    }
+    for path in &would_add {
+        eprintln!("  would add {}", path.display());
+    }
+
+    let pruned: Vec<_> = if prune {
+        db.find_orphaned()
+            .into_iter()
+            .filter(|p| p.path.starts_with(dir))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    for p in &pruned {
+        eprintln!("  would remove {} ({})", p.name, p.path.display());
+    }
+
+    eprintln!(
+        "Dry run: found {} projects, would add {} and remove {}",
+        found.len(),
+        would_add.len(),
+        pruned.len()
+    );
+    Ok(())
+}
+
+/// Live `prj scan` progress on stderr, redrawn at most every 100ms and
+/// only when stderr is a terminal.
+struct ScanProgressLine {
+    enabled: bool,
+    last_draw: Mutex<Option<Instant>>,
+}
+
+impl ScanProgressLine {
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    fn new() -> Self {
+        Self {
+            enabled: io::stderr().is_terminal(),
+            last_draw: Mutex::new(None),
+        }
+    }
+
+    fn update(&self, progress: &scan::ScanProgress) {
+        if !self.enabled {
+            return;
+        }
+        let Ok(mut last_draw) = self.last_draw.try_lock() else {
+            return;
+        };
+        let now = Instant::now();
+        if last_draw.is_some_and(|t| now - t < Self::INTERVAL) {
+            return;
+        }
+        *last_draw = Some(now);
+        let current = progress.current.display().to_string();
+        let tail: String = {
+            let chars: Vec<char> = current.chars().collect();
+            let skip = chars.len().saturating_sub(60);
+            chars[skip..].iter().collect()
+        };
+        eprint!(
+            "\r\x1b[2K  scanning: {} dirs, {} projects  {tail}",
+            progress.dirs_visited, progress.projects_found
+        );
+        let _ = io::stderr().flush();
+    }
+
+    fn finish(&self) {
+        if self.enabled {
+            eprint!("\r\x1b[2K");
+        }
+    }
+}
+
 fn cmd_new(config: &Config, git_args: &str) -> color_eyre::Result<()> {
     let args = shell_words::split(git_args)
         .map_err(|e| PrjError::CloneFailed(format!("failed to parse args: {e}")))?;
@@ -119,10 +401,11 @@ fn cmd_new(config: &Config, git_args: &str) -> color_eyre::Result<()> {
 
     let dest = determine_clone_dest(&args)?;
 
-    let mut db = ProjectDatabase::load(config)?;
-    let project = db.register(&dest, None)?;
+    let mut db = ProjectDatabase::lock(config)?;
+    let project = db.register(&dest, None, &config.build_systems)?;
     eprintln!("Registered: {} ({})", project.name, project.path.display());
-    db.save(config)?;
+    let summary = format!("cloned {}", project.name);
+    db.commit(Operation::New, summary)?;
     Ok(())
 }
 
@@ -152,9 +435,9 @@ fn determine_clone_dest(args: &[String]) -> Result<PathBuf, PrjError> {
 }
 
 fn cmd_remove(config: &Config, name: &str) -> color_eyre::Result<()> {
-    let mut db = ProjectDatabase::load(config)?;
+    let mut db = ProjectDatabase::lock(config)?;
     let removed = db.remove(name)?;
-    db.save(config)?;
+    db.commit(Operation::Remove, format!("removed {}", removed.name))?;
     eprintln!(
         "Removed project: {} ({})",
         removed.name,
@@ -163,15 +446,186 @@ fn cmd_remove(config: &Config, name: &str) -> color_eyre::Result<()> {
     Ok(())
 }
 
-fn cmd_list(config: &Config, plain: bool, tag: Option<&str>) -> color_eyre::Result<()> {
+fn cmd_rename(config: &Config, project: &str, new_name: &str) -> color_eyre::Result<()> {
+    let mut db = ProjectDatabase::lock(config)?;
+    let old_name = db.resolve(project)?.name.clone();
+    db.rename(&old_name, new_name)?;
+    db.commit(
+        Operation::Rename,
+        format!("renamed {old_name} to {new_name}"),
+    )?;
+    eprintln!("Renamed project: {old_name} -> {new_name}");
+    Ok(())
+}
+
+fn cmd_mv(
+    config: &Config,
+    project: &str,
+    new_path: &Path,
+    move_dir: bool,
+) -> color_eyre::Result<()> {
+    let mut db = ProjectDatabase::lock(config)?;
+    let (name, old_path) = {
+        let p = db.resolve(project)?;
+        (p.name.clone(), p.path.clone())
+    };
+    db.check_relocate(&name, new_path)?;
+
+    if !move_dir {
+        let p = db.relocate(&name, new_path, &config.build_systems)?;
+        eprintln!("Relocated project: {} ({})", p.name, p.path.display());
+        let summary = format!("moved {name} to {}", p.path.display());
+        db.commit(Operation::Move, summary)?;
+        return Ok(());
+    }
+
+    if new_path.exists() {
+        return Err(PrjError::DestinationExists(new_path.to_path_buf()).into());
+    }
+    if let Some(parent) = new_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::rename(&old_path, new_path).map_err(|e| {
+        if e.kind() == io::ErrorKind::CrossesDevices {
+            PrjError::CrossDeviceMove {
+                from: old_path.clone(),
+                to: new_path.to_path_buf(),
+            }
+        } else {
+            PrjError::Io(e)
+        }
+    })?;
+    eprintln!("Moved {} -> {}", old_path.display(), new_path.display());
+
+    let relocated = db
+        .relocate(&name, new_path, &config.build_systems)
+        .map(|p| (p.name.clone(), p.path.clone()))
+        .and_then(|(name, path)| {
+            let moved = MovedDir {
+                from: old_path.clone(),
+                to: path.clone(),
+            };
+            db.commit_move(format!("moved {name} to {}", path.display()), moved)?;
+            Ok((name, path))
+        });
+    match relocated {
+        Ok((name, path)) => {
+            eprintln!("Relocated project: {name} ({})", path.display());
+            Ok(())
+        }
+        Err(e) => {
+            // Keep the directory where the database says it is
+            match std::fs::rename(new_path, &old_path) {
+                Ok(()) => eprintln!("Moved {} back", old_path.display()),
+                Err(back) => eprintln!(
+                    "warning: could not move {} back to {}: {back}",
+                    new_path.display(),
+                    old_path.display()
+                ),
+            }
+            Err(e.into())
+        }
+    }
+}
+
+fn cmd_refresh(config: &Config, project: Option<&str>, all: bool) -> color_eyre::Result<()> {
+    let mut db = ProjectDatabase::lock(config)?;
+
+    let targets: Vec<_> = if let Some(name) = project {
+        vec![db.resolve_mut(name)?]
+    } else if all {
+        db.projects.iter_mut().collect()
+    } else {
+        return Err(PrjError::NoTargetProjects.into());
+    };
+
+    let total = targets.len();
+    let summary = match project {
+        Some(_) => format!("refreshed {}", targets[0].name),
+        None => format!("refreshed {total} projects"),
+    };
+    let mut changed = 0;
+    let mut refreshed = Vec::new();
+    for p in targets {
+        if !p.path.is_dir() {
+            eprintln!("{}: path no longer exists (see `prj gc`)", p.name);
+            continue;
+        }
+        refreshed.push(p.name.clone());
+        let diff = p.refresh(&config.build_systems);
+        if diff.is_empty() {
+            if project.is_some() {
+                eprintln!("{}: up to date", p.name);
+            }
+            continue;
+        }
+        changed += 1;
+        eprintln!("{}:", p.name);
+        for change in &diff.changes {
+            if !change.added.is_empty() {
+                eprintln!("  + {}: {}", change.field, change.added.join(", "));
+            }
+            if !change.removed.is_empty() {
+                eprintln!("  - {}: {}", change.field, change.removed.join(", "));
+            }
+        }
+    }
+
+    // Pick up workspace members added since the project was registered
+    for name in &refreshed {
+        for member in db.register_members(name, &config.build_systems)? {
+            eprintln!("{name}: + member {member}");
+        }
+    }
+
+    db.commit(Operation::Refresh, summary)?;
+    if project.is_none() {
+        eprintln!("Refreshed {total} projects, {changed} changed");
+    }
+    Ok(())
+}
+
+/// Escape a value for tab-separated output (`prj list --plain`, `prj meta
+/// get`): backslash, tab, newline,
+/// carriage return and comma become `\\`, `\t`, `\n`, `\r` and `\,`, so
+/// values can't break the tab- and comma-separated fields.
+fn escape_field(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            ',' => out.push_str("\\,"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn cmd_list(
+    config: &Config,
+    plain: bool,
+    tag: Option<&str>,
+    lang: Option<&str>,
+    json: bool,
+) -> color_eyre::Result<()> {
     let mut db = ProjectDatabase::load(config)?;
 
-    // Filter by tag if specified
+    // Filter by tag and language if specified
     if let Some(tag) = tag {
-        db.projects.retain(|p| p.tags.iter().any(|t| t == tag));
+        let tag = tags::normalize(tag)?;
+        db.projects.retain(|p| p.has_tag(&tag));
     }
+    if let Some(lang) = lang {
+        db.projects.retain(|p| p.has_language(lang));
+    }
+    worktree::group_worktrees(&mut db.projects);
 
-    if plain || !std::io::stderr().is_terminal() {
+    if json {
+        println!("{}", serde_json::to_string_pretty(&db.projects)?);
+    } else if plain || !std::io::stderr().is_terminal() {
         if db.projects.is_empty() {
             eprintln!("No projects registered. Use `prj add` or `prj scan` to add projects.");
             return Ok(());
@@ -194,37 +648,99 @@ fn cmd_list(config: &Config, plain: bool, tag: Option<&str>) -> color_eyre::Resu
             } else {
                 p.tags.join(",")
             };
+            let meta = if p.metadata.is_empty() {
+                "-".to_string()
+            } else {
+                p.metadata
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", escape_field(k), escape_field(v)))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            };
+            let description = escape_field(p.description.as_deref().unwrap_or("-"));
+            let toolchains = if p.toolchains.is_empty() {
+                "-".to_string()
+            } else {
+                p.toolchains
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", escape_field(k), escape_field(v)))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            };
             println!(
-                "{}\t{}\t{}\t{}\t{}",
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
                 p.name,
                 p.path.display(),
                 if vcs.is_empty() { "-" } else { &vcs },
                 if bs.is_empty() { "-" } else { &bs },
                 tags,
+                meta,
+                description,
+                toolchains,
             );
         }
     } else if let Some(path) = crate::tui::run_list(&mut db.projects, config)? {
+        record_visit(config, &path);
         println!("{}", path.display());
     }
 
     Ok(())
 }
 
-fn cmd_stats(config: &Config, project: Option<&str>, json: bool) -> color_eyre::Result<()> {
-    let db = ProjectDatabase::load(config)?;
+fn cmd_stats(
+    config: &Config,
+    project: Option<&str>,
+    tag: Option<&str>,
+    lang: Option<&str>,
+    json: bool,
+    refresh: bool,
+    record: bool,
+) -> color_eyre::Result<()> {
+    let mut db = ProjectDatabase::load(config)?;
+    let cache_path = StatsCache::path_for(&config.database_path());
+    let mut cache = StatsCache::load(&cache_path);
+    cache.retain_projects(&db.projects);
 
+    if let Some(tag) = tag {
+        let tag = tags::normalize(tag)?;
+        db.projects.retain(|p| p.has_tag(&tag));
+    }
+    if let Some(lang) = lang {
+        db.projects.retain(|p| p.has_language(lang));
+    }
+
+    let now = chrono::Utc::now();
+    let mut snapshots = Vec::new();
     if let Some(name) = project {
-        let proj = db
-            .find(name)
-            .ok_or_else(|| PrjError::ProjectNotFound(name.to_string()))?;
-        let s = stats::collect_project_stats(proj);
+        let proj = db.resolve(name)?;
+        if refresh {
+            cache.entries.remove(&proj.path);
+        }
+        let (s, entry) = stats::collect_project_stats_cached(proj, &cache);
+        cache.entries.insert(proj.path.clone(), entry);
+        if record {
+            snapshots.push(Snapshot::new(proj, &s, now));
+        }
         if json {
             println!("{}", serde_json::to_string_pretty(&s)?);
         } else {
             print_project_stats(&s);
         }
     } else {
-        let overview = stats::collect_overview_stats(&db.projects);
+        if refresh {
+            for p in &db.projects {
+                cache.entries.remove(&p.path);
+            }
+        }
+        let overview = stats::collect_overview_stats_cached(&db.projects, &mut cache);
+        if record {
+            snapshots.extend(
+                db.projects
+                    .iter()
+                    .zip(&overview.projects)
+                    .map(|(p, s)| Snapshot::new(p, s, now)),
+            );
+        }
         if json {
             println!("{}", serde_json::to_string_pretty(&overview)?);
         } else {
@@ -232,26 +748,269 @@ fn cmd_stats(config: &Config, project: Option<&str>, json: bool) -> color_eyre::
         }
     }
 
+    // The cache only saves time; failing to write it isn't an error
+    if let Err(e) = cache.save(&cache_path) {
+        eprintln!("warning: {e}");
+    }
+
+    if record {
+        let count = snapshots.len();
+        StatsHistory::append(
+            &StatsHistory::path_for(&config.database_path()),
+            snapshots,
+            config.lock_timeout(),
+        )?;
+        eprintln!(
+            "Recorded {count} snapshot{}",
+            if count == 1 { "" } else { "s" }
+        );
+    }
+
+    Ok(())
+}
+
+fn cmd_stats_history(
+    config: &Config,
+    project: Option<&str>,
+    tag: Option<&str>,
+    lang: Option<&str>,
+    since: Option<chrono::TimeDelta>,
+    json: bool,
+    csv: bool,
+) -> color_eyre::Result<()> {
+    let mut db = ProjectDatabase::load(config)?;
+    if let Some(tag) = tag {
+        let tag = tags::normalize(tag)?;
+        db.projects.retain(|p| p.has_tag(&tag));
+    }
+    if let Some(lang) = lang {
+        db.projects.retain(|p| p.has_language(lang));
+    }
+    let paths: Vec<PathBuf> = match project {
+        Some(name) => vec![db.resolve(name)?.path.clone()],
+        None => db.projects.iter().map(|p| p.path.clone()).collect(),
+    };
+
+    let history = StatsHistory::load(&StatsHistory::path_for(&config.database_path()))?;
+    let since = since.and_then(|age| chrono::Utc::now().checked_sub_signed(age));
+    let series: Vec<Vec<&Snapshot>> = history
+        .series(since)
+        .into_iter()
+        .filter(|s| paths.contains(&s[0].path))
+        .collect();
+
+    if json {
+        let out: Vec<serde_json::Value> = series
+            .iter()
+            .map(|s| {
+                let latest = s[s.len() - 1];
+                serde_json::json!({
+                    "name": latest.name,
+                    "path": latest.path,
+                    "snapshots": s,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&out)?);
+        return Ok(());
+    }
+    if csv {
+        println!("at,name,path,code_lines,disk_bytes,artifact_bytes,dirty,languages");
+        for snapshot in series.iter().flatten() {
+            let languages: Vec<String> = snapshot
+                .languages
+                .iter()
+                .map(|(lang, code)| format!("{lang}:{code}"))
+                .collect();
+            println!(
+                "{},{},{},{},{},{},{},{}",
+                snapshot.at.to_rfc3339(),
+                csv_field(&snapshot.name),
+                csv_field(&snapshot.path.display().to_string()),
+                snapshot.code_lines,
+                snapshot.disk_bytes,
+                snapshot.artifact_bytes,
+                snapshot.dirty.map(|d| d.to_string()).unwrap_or_default(),
+                csv_field(&languages.join(";")),
+            );
+        }
+        return Ok(());
+    }
+
+    if series.is_empty() {
+        eprintln!("No stats recorded yet. Use `prj stats --record` to take a snapshot.");
+        return Ok(());
+    }
+
+    eprintln!(
+        "  {:<20} {:>5} {:>10} {:>9} {:<24} {:>10} {:>10} {:<24}",
+        "Name", "Snaps", "LOC", "Δ", "Trend", "Artifacts", "Δ", "Trend"
+    );
+    eprintln!("  {}", "-".repeat(119));
+    for s in &series {
+        let (first, latest) = (s[0], s[s.len() - 1]);
+        let loc: Vec<u64> = s.iter().map(|snap| snap.code_lines as u64).collect();
+        let artifacts: Vec<u64> = s.iter().map(|snap| snap.artifact_bytes).collect();
+        let loc_delta = latest.code_lines as i64 - first.code_lines as i64;
+        let artifact_delta = latest.artifact_bytes as i64 - first.artifact_bytes as i64;
+        eprintln!(
+            "  {:<20} {:>5} {:>10} {:>9} {:<24} {:>10} {:>10} {:<24}",
+            latest.name,
+            s.len(),
+            latest.code_lines,
+            format!("{loc_delta:+}"),
+            sparkline(&loc),
+            ByteSize(latest.artifact_bytes).to_string(),
+            format_byte_delta(artifact_delta),
+            sparkline(&artifacts),
+        );
+    }
+
+    if let [s] = series.as_slice() {
+        eprintln!();
+        eprintln!(
+            "  {:<17} {:>10} {:>10} {:>10} {:<6}",
+            "Recorded", "LOC", "Disk", "Artifacts", "Status"
+        );
+        for snap in s {
+            let status = match snap.dirty {
+                Some(true) => "dirty",
+                Some(false) => "clean",
+                None => "-",
+            };
+            eprintln!(
+                "  {:<17} {:>10} {:>10} {:>10} {:<6}",
+                snap.at
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M"),
+                snap.code_lines,
+                ByteSize(snap.disk_bytes).to_string(),
+                ByteSize(snap.artifact_bytes).to_string(),
+                status,
+            );
+        }
+    }
+
     Ok(())
 }
 
+/// Unicode sparkline of `values`, averaged down to at most 24 characters.
+fn sparkline(values: &[u64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    const WIDTH: usize = 24;
+
+    let points: Vec<u64> = if values.len() <= WIDTH {
+        values.to_vec()
+    } else {
+        (0..WIDTH)
+            .map(|i| {
+                let bucket = &values[i * values.len() / WIDTH..(i + 1) * values.len() / WIDTH];
+                bucket.iter().sum::<u64>() / bucket.len() as u64
+            })
+            .collect()
+    };
+    let (Some(&min), Some(&max)) = (points.iter().min(), points.iter().max()) else {
+        return String::new();
+    };
+    points
+        .iter()
+        .map(|&v| {
+            if max == min {
+                BARS[0]
+            } else {
+                BARS[((v - min) * 7 / (max - min)) as usize]
+            }
+        })
+        .collect()
+}
+
+/// Signed byte count such as `+1.2 MiB` or `-300 B`.
+fn format_byte_delta(delta: i64) -> String {
+    let sign = if delta < 0 { '-' } else { '+' };
+    format!("{sign}{}", ByteSize(delta.unsigned_abs()))
+}
+
+/// Quote a CSV field if it contains a separator, quote or newline.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
 fn print_project_stats(s: &stats::ProjectStats) {
     eprintln!("Project: {}", s.name);
+    if let Some(description) = &s.description {
+        eprintln!("  {description}");
+    }
+    for (key, value) in &s.metadata {
+        eprintln!("  {key}: {value}");
+    }
     eprintln!();
 
-    if let Some(git) = &s.git {
-        let branch = git.branch.as_deref().unwrap_or("(detached)");
-        let status = if git.is_dirty { "dirty" } else { "clean" };
-        eprintln!("  Git: {branch} ({status})");
-        if git.is_dirty {
+    if let Some(vcs) = &s.vcs {
+        let branch = vcs.branch.as_deref().unwrap_or("(detached)");
+        let status = if vcs.is_dirty { "dirty" } else { "clean" };
+        eprintln!("  {}: {branch} ({status})", vcs.vcs);
+        if vcs.is_dirty {
             eprintln!(
                 "    changed: {}, staged: {}, untracked: {}",
-                git.changed, git.staged, git.untracked
+                vcs.changed, vcs.staged, vcs.untracked
             );
         }
-        if git.ahead > 0 || git.behind > 0 {
-            eprintln!("    ahead: {}, behind: {}", git.ahead, git.behind);
+        if vcs.ahead > 0 || vcs.behind > 0 {
+            eprintln!("    ahead: {}, behind: {}", vcs.ahead, vcs.behind);
+        }
+        for sm in &vcs.submodules {
+            eprintln!("    submodule {}: {}", sm.path, sm.state);
+        }
+        if let Some(commit) = &vcs.detached_at {
+            eprintln!("    HEAD detached at {commit}");
+        }
+        if let Some(state) = vcs.state {
+            eprintln!("    {state} in progress");
         }
+        if vcs.conflicts > 0 {
+            eprintln!("    conflicted paths: {}", vcs.conflicts);
+        }
+        if vcs.stashes > 0 {
+            eprintln!("    stash entries: {}", vcs.stashes);
+        }
+        if !vcs.no_upstream.is_empty() {
+            eprintln!("    no upstream: {}", vcs.no_upstream.join(", "));
+        }
+    }
+
+    if let Some(a) = &s.activity {
+        eprintln!(
+            "  Last commit: {} ago by {} ({})",
+            format_age(chrono::Utc::now() - a.last_commit),
+            a.last_author,
+            a.last_commit
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d")
+        );
+        eprintln!(
+            "    commits: {} in 7d, {} in 30d, {} in 90d",
+            a.commits_7d, a.commits_30d, a.commits_90d
+        );
+        eprintln!(
+            "    {}{} commits by {} authors since {}",
+            a.total_commits,
+            if a.truncated { "+" } else { "" },
+            a.authors,
+            a.first_commit
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d")
+        );
+    }
+
+    if !s.toolchains.is_empty() {
+        eprintln!(
+            "  Toolchains: {}",
+            toolchain::toolchain_list(&s.toolchains).join(", ")
+        );
     }
 
     eprintln!();
@@ -269,6 +1028,14 @@ fn print_project_stats(s: &stats::ProjectStats) {
         s.disk.total_display(),
         s.disk.artifact_display()
     );
+
+    if let Some(age) = s.cache_age() {
+        eprintln!();
+        eprintln!(
+            "  (lines of code and disk usage cached {} ago; --refresh to recompute)",
+            format_age(age)
+        );
+    }
 }
 
 fn print_overview_stats(o: &stats::OverviewStats) {
@@ -283,34 +1050,63 @@ fn print_overview_stats(o: &stats::OverviewStats) {
     eprintln!();
 
     eprintln!(
-        "  {:<20} {:<12} {:<10} {:<10} {:<10}",
-        "Name", "Branch", "Status", "LOC", "Disk"
+        "  {:<20} {:<12} {:<10} {:<10} {:<10} {:<10}",
+        "Name", "Branch", "Status", "LOC", "Disk", "Cached"
     );
-    eprintln!("  {}", "-".repeat(62));
+    eprintln!("  {}", "-".repeat(73));
 
     for s in &o.projects {
         let branch = s
-            .git
+            .vcs
             .as_ref()
-            .and_then(|g| g.branch.as_deref())
+            .and_then(|v| v.branch.as_deref())
             .unwrap_or("-");
         let status = s
-            .git
+            .vcs
             .as_ref()
-            .map(|g| if g.is_dirty { "dirty" } else { "clean" })
+            .map(|v| if v.is_dirty { "dirty" } else { "clean" })
             .unwrap_or("-");
+        let cached = s
+            .cache_age()
+            .map(|age| format!("{} ago", format_age(age)))
+            .unwrap_or_else(|| "-".to_string());
 
         eprintln!(
-            "  {:<20} {:<12} {:<10} {:<10} {:<10}",
+            "  {:<20} {:<12} {:<10} {:<10} {:<10} {:<10}",
             s.name,
             branch,
             status,
             s.loc.total_code,
             s.disk.total_display(),
+            cached,
         );
     }
 }
 
+/// One-line summary of commit activity for `prj status --long`.
+fn activity_summary(a: &stats::GitActivity) -> String {
+    format!(
+        "last commit {} ago by {}, {} commits in 30d, {} authors, {}{} commits total",
+        format_age(chrono::Utc::now() - a.last_commit),
+        a.last_author,
+        a.commits_30d,
+        a.authors,
+        a.total_commits,
+        if a.truncated { "+" } else { "" },
+    )
+}
+
+/// Compact age such as `45s`, `12m`, `3h` or `5d`.
+pub fn format_age(age: chrono::TimeDelta) -> String {
+    let secs = age.num_seconds().max(0);
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
 fn cmd_init(shell: &str, cmd: &str) -> color_eyre::Result<()> {
     let script = crate::shell::generate_init(shell, cmd)?;
     print!("{script}");
@@ -318,52 +1114,198 @@ fn cmd_init(shell: &str, cmd: &str) -> color_eyre::Result<()> {
 }
 
 fn cmd_picker(config: &Config) -> color_eyre::Result<()> {
-    let db = ProjectDatabase::load(config)?;
+    let mut db = ProjectDatabase::load(config)?;
     if db.projects.is_empty() {
         eprintln!("No projects registered. Use `prj add` or `prj scan` to add projects.");
         return Ok(());
     }
-    if let Some(path) = crate::tui::run_picker(&db.projects)? {
+    worktree::group_worktrees(&mut db.projects);
+    if let Some(path) = crate::tui::run_picker(&db.projects, config)? {
+        record_visit(config, &path);
         println!("{}", path.display());
     }
     Ok(())
 }
 
+/// Bump a project's frecency after it was jumped to. Failing to record a
+/// visit must never stop the jump itself, so errors are only reported.
+fn record_visit(config: &Config, path: &Path) {
+    let result = ProjectDatabase::lock(config).and_then(|mut db| {
+        db.record_visit(path)?;
+        db.save()
+    });
+    if let Err(e) = result {
+        eprintln!("warning: could not record visit: {e}");
+    }
+}
+
 // --- Phase 1: Tags ---
 
 fn cmd_tag(config: &Config, project: &str, tags: &[String]) -> color_eyre::Result<()> {
-    let mut db = ProjectDatabase::load(config)?;
+    let mut db = ProjectDatabase::lock(config)?;
     db.add_tags(project, tags)?;
-    let p = db.find(project).expect("project was just found");
+    let p = db.resolve(project)?;
     eprintln!("Tags for {}: {}", p.name, p.tags.join(", "));
-    db.save(config)?;
+    let summary = format!("tagged {} with {}", p.name, tags.join(", "));
+    db.commit(Operation::Tag, summary)?;
     Ok(())
 }
 
 fn cmd_untag(config: &Config, project: &str, tags: &[String]) -> color_eyre::Result<()> {
-    let mut db = ProjectDatabase::load(config)?;
+    let mut db = ProjectDatabase::lock(config)?;
     db.remove_tags(project, tags)?;
-    let p = db.find(project).expect("project was just found");
+    let p = db.resolve(project)?;
     let tag_display = if p.tags.is_empty() {
         "(none)".to_string()
     } else {
         p.tags.join(", ")
     };
     eprintln!("Tags for {}: {tag_display}", p.name);
-    db.save(config)?;
+    let summary = format!("untagged {} from {}", tags.join(", "), p.name);
+    db.commit(Operation::Untag, summary)?;
+    Ok(())
+}
+
+fn cmd_tags(config: &Config, action: Option<TagsAction>) -> color_eyre::Result<()> {
+    match action {
+        None => {
+            let db = ProjectDatabase::load(config)?;
+            let counts = db.tag_counts();
+            if counts.is_empty() {
+                eprintln!("No tags in use. Add some with `prj tag`.");
+            }
+            for (tag, count) in counts {
+                println!("{tag}\t{count}");
+            }
+        }
+        Some(TagsAction::Rename { old, new }) => {
+            let mut db = ProjectDatabase::lock(config)?;
+            let changed = db.rename_tag(&old, &new)?;
+            if changed == 0 {
+                bail!("no project has tag {old}");
+            }
+            eprintln!("Renamed tag {old} -> {new} on {changed} projects");
+            db.commit(Operation::Tags, format!("renamed tag {old} to {new}"))?;
+        }
+        Some(TagsAction::Delete { tag }) => {
+            let mut db = ProjectDatabase::lock(config)?;
+            let changed = db.delete_tag(&tag)?;
+            if changed == 0 {
+                bail!("no project has tag {tag}");
+            }
+            eprintln!("Deleted tag {tag} from {changed} projects");
+            db.commit(Operation::Tags, format!("deleted tag {tag}"))?;
+        }
+    }
+    Ok(())
+}
+
+fn cmd_meta(config: &Config, action: MetaAction) -> color_eyre::Result<()> {
+    match action {
+        MetaAction::Set {
+            project,
+            key,
+            value,
+        } => {
+            let mut db = ProjectDatabase::lock(config)?;
+            let p = db.resolve_mut(&project)?;
+            p.set_meta(&key, &value)?;
+            eprintln!("{}: {key} = {value}", p.name);
+            let summary = format!("set {key} on {}", p.name);
+            db.commit(Operation::Meta, summary)?;
+        }
+        MetaAction::Unset { project, key } => {
+            let mut db = ProjectDatabase::lock(config)?;
+            let p = db.resolve_mut(&project)?;
+            if p.unset_meta(&key) {
+                eprintln!("{}: removed {key}", p.name);
+                let summary = format!("unset {key} on {}", p.name);
+                db.commit(Operation::Meta, summary)?;
+            } else {
+                eprintln!("{}: {key} is not set", p.name);
+            }
+        }
+        MetaAction::Get { project, key } => {
+            let db = ProjectDatabase::load(config)?;
+            let p = db.resolve(&project)?;
+            match key {
+                Some(key) => {
+                    let value = p
+                        .get_meta(&key)
+                        .ok_or_else(|| eyre!("{}: {key} is not set", p.name))?;
+                    println!("{value}");
+                }
+                None => {
+                    if let Some(description) = &p.description {
+                        println!("{DESCRIPTION_KEY}\t{}", escape_field(description));
+                    }
+                    for (k, v) in &p.metadata {
+                        println!("{}\t{}", escape_field(k), escape_field(v));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// --- Worktrees ---
+
+fn cmd_worktree(config: &Config, action: WorktreeAction) -> color_eyre::Result<()> {
+    match action {
+        WorktreeAction::Add {
+            project,
+            branch,
+            path,
+        } => {
+            let db = ProjectDatabase::load(config)?;
+            let proj = db.resolve(&project)?;
+            if !proj.vcs.contains(&detect::VcsType::Git) {
+                bail!("{} is not a git repository", proj.name);
+            }
+            // Worktrees of a worktree belong to the same main repository
+            let main = proj
+                .worktree_of
+                .clone()
+                .unwrap_or_else(|| proj.path.clone());
+            let dest = match path {
+                Some(path) => std::env::current_dir()?.join(path),
+                None => worktree::default_path(&main, &branch),
+            };
+            worktree::add(&main, &branch, &dest)?;
+
+            let mut db = ProjectDatabase::lock(config)?;
+            let added = db.register_disambiguated(&dest, None, &config.build_systems)?;
+            eprintln!("Registered: {} ({})", added.name, added.path.display());
+            let summary = format!("added worktree {} ({branch})", added.name);
+            db.commit(Operation::Worktree, summary)?;
+        }
+    }
     Ok(())
 }
 
 // --- Phase 3: Status ---
 
-fn cmd_status(config: &Config, json: bool) -> color_eyre::Result<()> {
+fn cmd_status(
+    config: &Config,
+    tag: Option<&str>,
+    long: bool,
+    json: bool,
+) -> color_eyre::Result<()> {
     use rayon::prelude::*;
     use serde::Serialize;
 
-    let db = ProjectDatabase::load(config)?;
+    let mut db = ProjectDatabase::load(config)?;
+    if let Some(tag) = tag {
+        let tag = tags::normalize(tag)?;
+        db.projects.retain(|p| p.has_tag(&tag));
+    }
 
     if db.projects.is_empty() {
-        eprintln!("No projects registered.");
+        match tag {
+            Some(tag) => eprintln!("No projects found with tag: {tag}"),
+            None => eprintln!("No projects registered."),
+        }
         return Ok(());
     }
 
@@ -371,6 +1313,8 @@ fn cmd_status(config: &Config, json: bool) -> color_eyre::Result<()> {
     struct StatusEntry {
         name: String,
         #[serde(skip_serializing_if = "Option::is_none")]
+        vcs: Option<detect::VcsType>,
+        #[serde(skip_serializing_if = "Option::is_none")]
         branch: Option<String>,
         status: String,
         changed: usize,
@@ -378,30 +1322,58 @@ fn cmd_status(config: &Config, json: bool) -> color_eyre::Result<()> {
         untracked: usize,
         ahead: usize,
         behind: usize,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        submodules: Vec<stats::SubmoduleStatus>,
+        stashes: usize,
+        conflicts: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        state: Option<stats::RepoState>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        detached_at: Option<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        no_upstream: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        activity: Option<stats::GitActivity>,
     }
 
     let entries: Vec<StatusEntry> = db
         .projects
         .par_iter()
         .map(|p| {
-            let git = stats::collect_git_status(&p.path);
-            match git {
-                Some(g) => StatusEntry {
+            let vcs = stats::collect_vcs_status(&p.path);
+            let activity = if long {
+                stats::collect_git_activity(&p.path)
+            } else {
+                None
+            };
+            match vcs {
+                Some(v) => StatusEntry {
                     name: p.name.clone(),
-                    branch: g.branch,
-                    status: if g.is_dirty {
+                    vcs: Some(v.vcs),
+                    status: if v.needs_attention() {
+                        "attention".to_string()
+                    } else if v.is_dirty {
                         "dirty".to_string()
                     } else {
                         "clean".to_string()
                     },
-                    changed: g.changed,
-                    staged: g.staged,
-                    untracked: g.untracked,
-                    ahead: g.ahead,
-                    behind: g.behind,
+                    branch: v.branch,
+                    changed: v.changed,
+                    staged: v.staged,
+                    untracked: v.untracked,
+                    ahead: v.ahead,
+                    behind: v.behind,
+                    submodules: v.submodules,
+                    stashes: v.stashes,
+                    conflicts: v.conflicts,
+                    state: v.state,
+                    detached_at: v.detached_at,
+                    no_upstream: v.no_upstream,
+                    activity,
                 },
                 None => StatusEntry {
                     name: p.name.clone(),
+                    vcs: None,
                     branch: None,
                     status: "no-vcs".to_string(),
                     changed: 0,
@@ -409,6 +1381,13 @@ fn cmd_status(config: &Config, json: bool) -> color_eyre::Result<()> {
                     untracked: 0,
                     ahead: 0,
                     behind: 0,
+                    submodules: Vec::new(),
+                    stashes: 0,
+                    conflicts: 0,
+                    state: None,
+                    detached_at: None,
+                    no_upstream: Vec::new(),
+                    activity,
                 },
             }
         })
@@ -420,15 +1399,24 @@ fn cmd_status(config: &Config, json: bool) -> color_eyre::Result<()> {
     }
 
     eprintln!(
-        "  {:<20} {:<15} {:<10} {:<8} {:<8} {:<10} {:<10}",
-        "Name", "Branch", "Status", "Changed", "Staged", "Untracked", "Ahead/Behind"
+        "  {:<20} {:<11} {:<15} {:<10} {:<8} {:<8} {:<10} {:<10}",
+        "Name", "VCS", "Branch", "Status", "Changed", "Staged", "Untracked", "Ahead/Behind"
     );
-    eprintln!("  {}", "-".repeat(81));
+    eprintln!("  {}", "-".repeat(93));
 
     for e in &entries {
-        let branch = e.branch.as_deref().unwrap_or("-");
+        let vcs = e
+            .vcs
+            .map(|v| v.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let branch = match (&e.branch, &e.detached_at) {
+            (Some(branch), _) => branch.clone(),
+            (None, Some(commit)) => format!("({commit})"),
+            (None, None) => "-".to_string(),
+        };
         let status_color = match e.status.as_str() {
-            "clean" => "\x1b[32m", // green
+            "clean" => "\x1b[32m",     // green
+            "attention" => "\x1b[35m", // magenta
             "dirty" => {
                 if e.staged > 0 {
                     "\x1b[31m" // red
@@ -445,9 +1433,33 @@ fn cmd_status(config: &Config, json: bool) -> color_eyre::Result<()> {
             "-".to_string()
         };
         eprintln!(
-            "  {:<20} {:<15} {status_color}{:<10}{reset} {:<8} {:<8} {:<10} {:<10}",
-            e.name, branch, e.status, e.changed, e.staged, e.untracked, ahead_behind
+            "  {:<20} {:<11} {:<15} {status_color}{:<10}{reset} {:<8} {:<8} {:<10} {:<10}",
+            e.name, vcs, branch, e.status, e.changed, e.staged, e.untracked, ahead_behind
         );
+        for sm in &e.submodules {
+            eprintln!("    \x1b[33msubmodule {}: {}{reset}", sm.path, sm.state);
+        }
+        if e.conflicts > 0 {
+            eprintln!("    \x1b[31m{} conflicted paths{reset}", e.conflicts);
+        }
+        if let Some(state) = e.state {
+            eprintln!("    \x1b[35m{state} in progress{reset}");
+        }
+        if let Some(commit) = &e.detached_at {
+            eprintln!("    \x1b[36mHEAD detached at {commit}{reset}");
+        }
+        if e.stashes > 0 {
+            eprintln!("    \x1b[34m{} stash entries{reset}", e.stashes);
+        }
+        if !e.no_upstream.is_empty() {
+            eprintln!(
+                "    \x1b[33mno upstream: {}{reset}",
+                e.no_upstream.join(", ")
+            );
+        }
+        if let Some(a) = &e.activity {
+            eprintln!("    \x1b[2m{}{reset}", activity_summary(a));
+        }
     }
 
     Ok(())
@@ -456,7 +1468,7 @@ fn cmd_status(config: &Config, json: bool) -> color_eyre::Result<()> {
 // --- Phase 4: GC ---
 
 fn cmd_gc(config: &Config, dry_run: bool) -> color_eyre::Result<()> {
-    let mut db = ProjectDatabase::load(config)?;
+    let db = ProjectDatabase::load(config)?;
     let orphaned = db.find_orphaned();
 
     if orphaned.is_empty() {
@@ -480,8 +1492,13 @@ fn cmd_gc(config: &Config, dry_run: bool) -> color_eyre::Result<()> {
     let mut input = String::new();
     io::stdin().read_line(&mut input)?;
     if input.trim().eq_ignore_ascii_case("y") {
+        // Re-read under the lock: the database may have changed while prompting.
+        let mut db = ProjectDatabase::lock(config)?;
         let removed = db.remove_orphaned();
-        db.save(config)?;
+        db.commit(
+            Operation::Gc,
+            format!("removed {} orphaned projects", removed.len()),
+        )?;
         eprintln!("Removed {} orphaned projects.", removed.len());
     } else {
         eprintln!("Cancelled.");
@@ -490,21 +1507,298 @@ fn cmd_gc(config: &Config, dry_run: bool) -> color_eyre::Result<()> {
     Ok(())
 }
 
+fn cmd_stale(
+    config: &Config,
+    older_than: chrono::TimeDelta,
+    tag: Option<&str>,
+    json: bool,
+    clean: bool,
+    archive: bool,
+    remove: bool,
+) -> color_eyre::Result<()> {
+    use rayon::prelude::*;
+    use serde::Serialize;
+
+    let mut db = ProjectDatabase::load(config)?;
+    match tag {
+        Some(tag) => {
+            let tag = tags::normalize(tag)?;
+            db.projects.retain(|p| p.has_tag(&tag));
+        }
+        None => db.projects.retain(|p| !p.has_tag(stale::ARCHIVED_TAG)),
+    }
+
+    let cutoff = chrono::Utc::now()
+        .checked_sub_signed(older_than)
+        .unwrap_or(chrono::DateTime::<chrono::Utc>::MIN_UTC);
+    let stale = stale::stale_projects(&db.projects, cutoff);
+    if stale.is_empty() {
+        eprintln!(
+            "No projects inactive for longer than {}.",
+            format_age(older_than)
+        );
+        return Ok(());
+    }
+    let disk: Vec<stats::DiskStats> = stale
+        .par_iter()
+        .map(|(p, _)| stats::collect_disk_stats(&p.path, &p.artifact_dirs))
+        .collect();
+
+    if json {
+        #[derive(Serialize)]
+        struct StaleEntry<'a> {
+            name: &'a str,
+            path: &'a Path,
+            last_active: Option<chrono::DateTime<chrono::Utc>>,
+            #[serde(flatten)]
+            activity: &'a stale::Activity,
+            disk_bytes: u64,
+            artifact_bytes: u64,
+        }
+
+        let entries: Vec<StaleEntry> = stale
+            .iter()
+            .zip(&disk)
+            .map(|((p, activity), d)| StaleEntry {
+                name: &p.name,
+                path: &p.path,
+                last_active: activity.latest(),
+                activity,
+                disk_bytes: d.total_bytes,
+                artifact_bytes: d.artifact_bytes,
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    eprintln!(
+        "  {:<20} {:<12} {:<8} {:<10} {:<10}",
+        "Name", "Last active", "From", "Disk", "Artifacts"
+    );
+    eprintln!("  {}", "-".repeat(64));
+    for ((p, activity), d) in stale.iter().zip(&disk) {
+        let last_active = activity
+            .latest()
+            .map(|at| format!("{} ago", format_age(chrono::Utc::now() - at)))
+            .unwrap_or_else(|| "never".to_string());
+        eprintln!(
+            "  {:<20} {:<12} {:<8} {:<10} {:<10}",
+            p.name,
+            last_active,
+            activity.latest_source().unwrap_or("-"),
+            d.total_display(),
+            d.artifact_display(),
+        );
+    }
+    let total_bytes: u64 = disk.iter().map(|d| d.total_bytes).sum();
+    let artifact_bytes: u64 = disk.iter().map(|d| d.artifact_bytes).sum();
+    eprintln!(
+        "\n{} stale projects holding {} ({} in artifacts).",
+        stale.len(),
+        ByteSize(total_bytes),
+        ByteSize(artifact_bytes)
+    );
+
+    let count = stale.len();
+    let prompt = if clean {
+        format!("Delete build artifacts of {count} stale projects?")
+    } else if archive {
+        format!("Tag {count} stale projects {}?", stale::ARCHIVED_TAG)
+    } else if remove {
+        format!("Unregister {count} stale projects?")
+    } else {
+        eprintln!("Use --clean, --archive or --remove to act on them.");
+        return Ok(());
+    };
+    eprint!("\n{prompt} [y/N] ");
+    io::stderr().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    if !input.trim().eq_ignore_ascii_case("y") {
+        eprintln!("Cancelled.");
+        return Ok(());
+    }
+
+    if clean {
+        let mut freed = 0u64;
+        for ((p, _), d) in stale.iter().zip(&disk) {
+            if d.artifact_bytes == 0 {
+                continue;
+            }
+            match clean::execute_clean(&p.path, &p.artifact_dirs) {
+                Ok(bytes) => freed += bytes,
+                Err(e) => eprintln!("{}: {e}", p.name),
+            }
+        }
+        eprintln!("Freed {}.", ByteSize(freed));
+    } else {
+        // Re-read under the lock: the database may have changed while
+        // prompting, so projects are found by path, not by a name that may
+        // since belong to another project.
+        let mut db = ProjectDatabase::lock(config)?;
+        let archived = [stale::ARCHIVED_TAG.to_string()];
+        let mut done = 0;
+        let mut failed = Vec::new();
+        for (p, _) in &stale {
+            let Some(name) = db
+                .projects
+                .iter()
+                .find(|q| q.path == p.path)
+                .map(|q| q.name.clone())
+            else {
+                failed.push(format!("{}: no longer registered", p.path.display()));
+                continue;
+            };
+            let result = if archive {
+                db.add_tags(&name, &archived)
+            } else {
+                db.remove(&name).map(|_| ())
+            };
+            match result {
+                Ok(()) => done += 1,
+                Err(e) => failed.push(format!("{name}: {e}")),
+            }
+        }
+
+        if done > 0 {
+            let (operation, summary) = if archive {
+                (
+                    Operation::Tag,
+                    format!("tagged {done} stale projects {}", stale::ARCHIVED_TAG),
+                )
+            } else {
+                (Operation::Remove, format!("removed {done} stale projects"))
+            };
+            db.commit(operation, summary)?;
+        }
+        if archive {
+            eprintln!("Tagged {done} projects {}.", stale::ARCHIVED_TAG);
+        } else {
+            eprintln!("Removed {done} projects.");
+        }
+        for failure in &failed {
+            eprintln!("  {failure}");
+        }
+        if !failed.is_empty() {
+            let action = if archive { "tagged" } else { "removed" };
+            bail!("{} stale projects could not be {action}", failed.len());
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_doctor(config: &Config, toolchains_only: bool) -> color_eyre::Result<()> {
+    let db = ProjectDatabase::load(config)?;
+    let mut problems = 0;
+
+    if !toolchains_only {
+        for p in db.find_orphaned() {
+            eprintln!("{}: path no longer exists ({})", p.name, p.path.display());
+            problems += 1;
+        }
+    }
+
+    let mut checker = toolchain::ToolchainChecker::new();
+    for p in &db.projects {
+        if !p.path.exists() {
+            continue;
+        }
+        for (tool, version) in &p.toolchains {
+            let check = checker.check(tool, version);
+            let installed = if check.installed.is_empty() {
+                "not installed".to_string()
+            } else {
+                format!("installed: {}", check.installed.join(", "))
+            };
+            match check.satisfied {
+                Some(true) => {}
+                Some(false) => {
+                    eprintln!("{}: {tool} {version} missing ({installed})", p.name);
+                    problems += 1;
+                }
+                None => eprintln!("{}: {tool} {version} unchecked ({installed})", p.name),
+            }
+        }
+    }
+
+    if problems > 0 {
+        bail!("{problems} problems found");
+    }
+    eprintln!("No problems found.");
+    Ok(())
+}
+
+// --- Undo history ---
+
+fn cmd_undo(config: &Config) -> color_eyre::Result<()> {
+    let mut db = ProjectDatabase::lock(config)?;
+    let (entry, renamed) = db.undo()?;
+    if let Some(moved) = &entry.moved_dir {
+        eprintln!(
+            "Moved {} back to {}",
+            moved.to.display(),
+            moved.from.display()
+        );
+    }
+    eprintln!(
+        "Undid {}: {} ({})",
+        entry.operation,
+        entry.summary,
+        entry
+            .at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+    );
+    for (old, new) in renamed {
+        eprintln!("Restored {old} as {new}: {old} is now another project's name");
+    }
+    Ok(())
+}
+
+fn cmd_history(config: &Config, limit: usize) -> color_eyre::Result<()> {
+    let journal = Journal::load(&Journal::path_for(&config.database_path()))?;
+    if journal.entries.is_empty() {
+        eprintln!("No history yet.");
+        return Ok(());
+    }
+    for entry in journal.entries.iter().rev().take(limit) {
+        println!(
+            "{}\t{}\t{}",
+            entry
+                .at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M"),
+            entry.operation,
+            entry.summary
+        );
+    }
+    Ok(())
+}
+
 // --- Phase 5: Clean ---
 
 fn cmd_clean(
     config: &Config,
     project: Option<&str>,
+    tag: Option<&str>,
     all: bool,
     dry_run: bool,
 ) -> color_eyre::Result<()> {
     let db = ProjectDatabase::load(config)?;
 
     let targets: Vec<_> = if let Some(name) = project {
-        let p = db
-            .find(name)
-            .ok_or_else(|| PrjError::ProjectNotFound(name.to_string()))?;
+        let p = db.resolve(name)?;
         vec![p]
+    } else if let Some(tag) = tag {
+        let filtered = db.tagged(&tags::normalize(tag)?);
+        if filtered.is_empty() {
+            eprintln!("No projects found with tag: {tag}");
+            return Ok(());
+        }
+        filtered
     } else if all {
         db.projects.iter().collect()
     } else {
@@ -514,11 +1808,13 @@ fn cmd_clean(
     let mut total_freed = 0u64;
 
     for p in &targets {
-        if p.artifact_dirs.is_empty() {
+        if p.artifact_dirs.is_empty() && p.shared_artifact_dirs.is_empty() {
             continue;
         }
         let preview = clean::preview_clean(&p.path, &p.artifact_dirs);
-        if preview.dirs.is_empty() {
+        let mut shared = preview.skipped_shared.clone();
+        shared.extend(p.shared_artifact_dirs.iter().cloned());
+        if preview.dirs.is_empty() && shared.is_empty() {
             continue;
         }
 
@@ -526,6 +1822,13 @@ fn cmd_clean(
         for (dir, size) in &preview.dirs {
             eprintln!("  {dir}: {}", ByteSize(*size));
         }
+        for dir in &shared {
+            eprintln!("  {dir}: shared outside the project, skipped");
+        }
+        if preview.dirs.is_empty() {
+            eprintln!();
+            continue;
+        }
         eprintln!("  Total: {}", ByteSize(preview.total_bytes));
 
         if !dry_run {
@@ -557,29 +1860,41 @@ fn cmd_run(
     config: &Config,
     cmd: &str,
     project: Option<&str>,
+    members: bool,
     tag: Option<&str>,
+    lang: Option<&str>,
     all: bool,
 ) -> color_eyre::Result<()> {
     let db = ProjectDatabase::load(config)?;
 
     let targets: Vec<_> = if let Some(name) = project {
-        let p = db
-            .find(name)
-            .ok_or_else(|| PrjError::ProjectNotFound(name.to_string()))?;
-        vec![p]
-    } else if let Some(tag) = tag {
-        let filtered: Vec<_> = db
-            .projects
-            .iter()
-            .filter(|p| p.tags.contains(&tag.to_string()))
-            .collect();
+        let p = db.resolve(name)?;
+        if members {
+            let members = db.members(p);
+            if members.is_empty() {
+                bail!("{} has no registered workspace members", p.name);
+            }
+            members
+        } else {
+            vec![p]
+        }
+    } else if tag.is_some() || lang.is_some() || all {
+        let mut filtered = match tag {
+            Some(tag) => db.tagged(&tags::normalize(tag)?),
+            None => db.projects.iter().collect(),
+        };
+        if let Some(lang) = lang {
+            filtered.retain(|p| p.has_language(lang));
+        }
         if filtered.is_empty() {
-            eprintln!("No projects found with tag: {tag}");
+            match (tag, lang) {
+                (Some(tag), None) => eprintln!("No projects found with tag: {tag}"),
+                (None, Some(lang)) => eprintln!("No projects found with language: {lang}"),
+                _ => eprintln!("No projects match the given filters."),
+            }
             return Ok(());
         }
         filtered
-    } else if all {
-        db.projects.iter().collect()
     } else {
         return Err(PrjError::NoTargetProjects.into());
     };
@@ -623,8 +1938,13 @@ fn cmd_export(
     config: &Config,
     output: Option<&std::path::Path>,
     base_dir: Option<&std::path::Path>,
+    tag: Option<&str>,
 ) -> color_eyre::Result<()> {
-    let db = ProjectDatabase::load(config)?;
+    let mut db = ProjectDatabase::load(config)?;
+    if let Some(tag) = tag {
+        let tag = tags::normalize(tag)?;
+        db.projects.retain(|p| p.has_tag(&tag));
+    }
 
     if db.projects.is_empty() {
         eprintln!("No projects to export.");
@@ -659,10 +1979,9 @@ fn cmd_import(
     let m = manifest::parse(&content)
         .map_err(|e| PrjError::Manifest(format!("invalid manifest: {e}")))?;
 
-    let mut db = ProjectDatabase::load(config)?;
     let targets = manifest::import_targets(&m, base_dir);
 
-    let mut cloned = 0;
+    let mut cloned = Vec::new();
     let mut skipped = 0;
 
     for (entry, target_path) in &targets {
@@ -688,17 +2007,7 @@ fn cmd_import(
                 .status()?;
 
             if status.success() {
-                match db.register(target_path, Some(&entry.name)) {
-                    Ok(_) => {
-                        if !entry.tags.is_empty() {
-                            let _ = db.add_tags(&entry.name, &entry.tags);
-                        }
-                        cloned += 1;
-                    }
-                    Err(e) => {
-                        eprintln!("    warning: cloned but failed to register: {e}");
-                    }
-                }
+                cloned.push((entry, target_path));
             } else {
                 eprintln!("    warning: git clone failed for {}", entry.name);
             }
@@ -708,8 +2017,82 @@ fn cmd_import(
         }
     }
 
-    db.save(config)?;
+    // Clones can take a while, so only lock the database to register them.
+    let mut db = ProjectDatabase::lock(config)?;
+    for (entry, target_path) in &cloned {
+        match db.register_disambiguated(target_path, Some(&entry.name), &config.build_systems) {
+            Ok(p) => {
+                if p.name != entry.name {
+                    eprintln!("  registered {} as {} (name in use)", entry.name, p.name);
+                }
+                let name = p.name.clone();
+                if !entry.tags.is_empty() {
+                    let _ = db.add_tags(&name, &entry.tags);
+                }
+                if let Some(p) = db.find_mut(&name) {
+                    p.description = entry.description.clone();
+                    p.metadata = entry.metadata.clone();
+                }
+            }
+            Err(e) => {
+                eprintln!(
+                    "    warning: cloned {} but failed to register: {e}",
+                    entry.name
+                );
+            }
+        }
+    }
+
+    let cloned = cloned.len();
+    db.commit(
+        Operation::Import,
+        format!("imported {cloned} projects from {}", file.display()),
+    )?;
     eprintln!("\nImport complete: cloned {cloned}, skipped {skipped}");
 
     Ok(())
 }
+
+// --- Database maintenance ---
+
+fn cmd_db_migrate(
+    config: &Config,
+    to: StorageBackend,
+    path: Option<&Path>,
+    force: bool,
+) -> color_eyre::Result<()> {
+    let dest = path
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| Config::default_database_path(to));
+    if dest == config.database_path() {
+        bail!("{} is the current database", dest.display());
+    }
+
+    let source = ProjectDatabase::lock(config)?;
+    let mut target = ProjectDatabase::lock_storage(storage::open_backend(to, &dest)?, config)?;
+    if !target.projects.is_empty() && !force {
+        bail!(
+            "{} already contains {} projects (use --force to overwrite)",
+            dest.display(),
+            target.projects.len()
+        );
+    }
+
+    target.projects = source.projects.clone();
+    target.save()?;
+    // Entries recorded against the target's previous contents no longer apply
+    Journal::default().save(&target.journal_path())?;
+
+    eprintln!(
+        "Copied {} projects from {} to {} ({to})",
+        target.projects.len(),
+        config.database_path().display(),
+        dest.display()
+    );
+    eprintln!("To switch, set in {}:", Config::config_path().display());
+    eprintln!("  storage = \"{to}\"");
+    if path.is_some() {
+        eprintln!("  database_path = {:?}", dest.display().to_string());
+    }
+    Ok(())
+}
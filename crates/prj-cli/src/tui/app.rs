@@ -3,6 +3,7 @@ use std::path::PathBuf;
 use std::process;
 use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use crossterm::event::{self, Event, KeyCode, KeyEventKind};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use crossterm::{cursor, execute};
@@ -14,13 +15,17 @@ use super::fuzzy::{FuzzyMatch, FuzzyMatcher};
 use super::view;
 use prj_core::clean;
 use prj_core::config::Config;
+use prj_core::journal::Operation;
 use prj_core::project::{Project, ProjectDatabase};
 use prj_core::stats::{self, ProjectStats};
+use prj_core::worktree;
 
 pub struct PickerState {
     pub query: String,
     pub filtered: Vec<FuzzyMatch>,
     pub selected: usize,
+    /// Checked-out branch of each project that is a linked worktree.
+    pub worktree_branches: Vec<Option<String>>,
 }
 
 pub enum ListMode {
@@ -29,7 +34,7 @@ pub enum ListMode {
         menu_selected: usize,
     },
     ViewingStats {
-        stats: ProjectStats,
+        stats: Box<ProjectStats>,
     },
     Confirming {
         action: &'static str,
@@ -47,13 +52,14 @@ pub enum PendingAction {
 
 pub struct ListState {
     pub selected: usize,
-    pub git_statuses: Vec<Option<stats::GitStatus>>,
+    pub vcs_statuses: Vec<Option<stats::VcsStatus>>,
+    pub last_commits: Vec<Option<DateTime<Utc>>>,
     pub mode: ListMode,
     pub message: Option<String>,
 }
 
 /// Run the fuzzy picker TUI on stderr. Returns the selected project path or None.
-pub fn run_picker(projects: &[Project]) -> color_eyre::Result<Option<PathBuf>> {
+pub fn run_picker(projects: &[Project], config: &Config) -> color_eyre::Result<Option<PathBuf>> {
     let mut stderr = io::stderr();
     execute!(stderr, EnterAlternateScreen, cursor::Hide)?;
     terminal::enable_raw_mode()?;
@@ -61,13 +67,38 @@ pub fn run_picker(projects: &[Project]) -> color_eyre::Result<Option<PathBuf>> {
     let backend = CrosstermBackend::new(io::stderr());
     let mut terminal = Terminal::new(backend)?;
 
-    let names: Vec<String> = projects.iter().map(|p| p.name.clone()).collect();
+    let haystacks: Vec<String> = projects.iter().map(search_text).collect();
+    let frecency: Vec<f64> = if config.frecency {
+        let now = Utc::now();
+        // Worktrees rank with their main repository so they stay under it
+        projects
+            .iter()
+            .map(|p| {
+                p.worktree_of
+                    .as_ref()
+                    .and_then(|main| projects.iter().find(|m| m.path == *main))
+                    .unwrap_or(p)
+                    .frecency(now)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let worktree_branches = projects
+        .iter()
+        .map(|p| {
+            p.worktree_of
+                .as_ref()
+                .and_then(|_| worktree::head_branch(&p.path))
+        })
+        .collect();
     let mut matcher = FuzzyMatcher::new();
 
     let mut state = PickerState {
         query: String::new(),
-        filtered: matcher.filter("", &names),
+        filtered: matcher.filter("", &haystacks, &frecency),
         selected: 0,
+        worktree_branches,
     };
 
     let result = loop {
@@ -99,12 +130,12 @@ pub fn run_picker(projects: &[Project]) -> color_eyre::Result<Option<PathBuf>> {
                 }
                 KeyCode::Backspace => {
                     state.query.pop();
-                    state.filtered = matcher.filter(&state.query, &names);
+                    state.filtered = matcher.filter(&state.query, &haystacks, &frecency);
                     state.selected = 0;
                 }
                 KeyCode::Char(c) => {
                     state.query.push(c);
-                    state.filtered = matcher.filter(&state.query, &names);
+                    state.filtered = matcher.filter(&state.query, &haystacks, &frecency);
                     state.selected = 0;
                 }
                 _ => {}
@@ -118,6 +149,20 @@ pub fn run_picker(projects: &[Project]) -> color_eyre::Result<Option<PathBuf>> {
     Ok(result)
 }
 
+/// Text the picker matches a query against: the name first, then the
+/// description and `key=value` metadata so those are searchable too.
+fn search_text(project: &Project) -> String {
+    let mut text = project.name.clone();
+    if let Some(description) = &project.description {
+        text.push(' ');
+        text.push_str(description);
+    }
+    for (key, value) in &project.metadata {
+        text.push_str(&format!(" {key}={value}"));
+    }
+    text
+}
+
 /// Run the interactive list TUI on stderr.
 /// Returns Some(path) if the user chose "cd to project".
 pub fn run_list(
@@ -136,14 +181,20 @@ pub fn run_list(
     let backend = CrosstermBackend::new(io::stderr());
     let mut terminal = Terminal::new(backend)?;
 
-    let git_statuses: Vec<Option<stats::GitStatus>> = projects
+    let vcs_statuses: Vec<Option<stats::VcsStatus>> = projects
         .iter()
-        .map(|p| stats::collect_git_status(&p.path))
+        .map(|p| stats::collect_vcs_status(&p.path))
+        .collect();
+
+    let last_commits = projects
+        .iter()
+        .map(|p| stats::last_commit_time(&p.path))
         .collect();
 
     let mut state = ListState {
         selected: 0,
-        git_statuses,
+        vcs_statuses,
+        last_commits,
         mode: ListMode::Browsing,
         message: None,
     };
@@ -206,7 +257,9 @@ pub fn run_list(
                                     ListAction::ViewStats => {
                                         let project = &projects[state.selected];
                                         let ps = stats::collect_project_stats(project);
-                                        state.mode = ListMode::ViewingStats { stats: ps };
+                                        state.mode = ListMode::ViewingStats {
+                                            stats: Box::new(ps),
+                                        };
                                     }
                                     ListAction::CleanArtifacts => {
                                         state.mode = ListMode::Confirming {
@@ -286,23 +339,34 @@ pub fn run_list(
                         KeyCode::Char('y') | KeyCode::Char('Y') => {
                             match on_confirm {
                                 PendingAction::Remove => {
-                                    let name = projects[state.selected].name.clone();
-                                    projects.remove(state.selected);
-                                    // Save database
-                                    let db = ProjectDatabase {
-                                        projects: projects.clone(),
-                                    };
-                                    let _ = db.save(config);
-                                    // Refresh git statuses
-                                    state.git_statuses = projects
+                                    let target = &projects[state.selected];
+                                    // Remove from the locked on-disk database rather than
+                                    // overwriting it with our (possibly stale) copy.
+                                    let saved = ProjectDatabase::lock(config).and_then(|mut db| {
+                                        db.remove(&target.path.to_string_lossy())?;
+                                        db.commit(
+                                            Operation::Remove,
+                                            format!("removed {}", target.name),
+                                        )
+                                    });
+                                    state.mode = ListMode::Browsing;
+                                    if let Err(e) = saved {
+                                        state.message =
+                                            Some(format!("Failed to remove {}: {e}", target.name));
+                                        continue;
+                                    }
+
+                                    let removed = projects.remove(state.selected);
+                                    // Refresh VCS statuses
+                                    state.vcs_statuses = projects
                                         .iter()
-                                        .map(|p| stats::collect_git_status(&p.path))
+                                        .map(|p| stats::collect_vcs_status(&p.path))
                                         .collect();
+                                    state.last_commits.remove(state.selected);
                                     if state.selected >= projects.len() && !projects.is_empty() {
                                         state.selected = projects.len() - 1;
                                     }
-                                    state.message = Some(format!("Removed: {name}"));
-                                    state.mode = ListMode::Browsing;
+                                    state.message = Some(format!("Removed: {}", removed.name));
                                     if projects.is_empty() {
                                         break None;
                                     }
@@ -3,6 +3,9 @@ use nucleo::{
     pattern::{CaseMatching, Normalization, Pattern},
 };
 
+/// How many fuzzy-score points one unit of `ln(1 + frecency)` is worth.
+const FRECENCY_WEIGHT: f64 = 12.0;
+
 pub struct FuzzyMatcher {
     matcher: Matcher,
 }
@@ -20,13 +23,27 @@ impl FuzzyMatcher {
     }
 
     /// Filter items by query, returning matching indices sorted by score (best first).
-    pub fn filter(&mut self, query: &str, items: &[String]) -> Vec<FuzzyMatch> {
+    ///
+    /// `frecency` holds one score per item and is blended into the fuzzy
+    /// score; with an empty query, items are ordered by frecency alone.
+    /// Ties keep their original order.
+    pub fn filter(&mut self, query: &str, items: &[String], frecency: &[f64]) -> Vec<FuzzyMatch> {
+        let boost = |i: usize| {
+            let f = frecency.get(i).copied().unwrap_or(0.0);
+            (f.ln_1p() * FRECENCY_WEIGHT) as u32
+        };
+
         if query.is_empty() {
-            return items
+            let mut results: Vec<FuzzyMatch> = items
                 .iter()
                 .enumerate()
-                .map(|(i, _)| FuzzyMatch { index: i, score: 0 })
+                .map(|(i, _)| FuzzyMatch {
+                    index: i,
+                    score: boost(i),
+                })
                 .collect();
+            results.sort_by_key(|m| std::cmp::Reverse(m.score));
+            return results;
         }
 
         let pattern = Pattern::new(
@@ -43,11 +60,14 @@ impl FuzzyMatcher {
                 let mut buf = Vec::new();
                 let haystack = Utf32Str::new(item, &mut buf);
                 let score = pattern.score(haystack, &mut self.matcher)?;
-                Some(FuzzyMatch { index: i, score })
+                Some(FuzzyMatch {
+                    index: i,
+                    score: score + boost(i),
+                })
             })
             .collect();
 
-        results.sort_by(|a, b| b.score.cmp(&a.score));
+        results.sort_by_key(|m| std::cmp::Reverse(m.score));
         results
     }
 }
@@ -8,8 +8,8 @@ use std::path::PathBuf;
 use prj_core::config::Config;
 use prj_core::project::Project;
 
-pub fn run_picker(projects: &[Project]) -> color_eyre::Result<Option<PathBuf>> {
-    app::run_picker(projects)
+pub fn run_picker(projects: &[Project], config: &Config) -> color_eyre::Result<Option<PathBuf>> {
+    app::run_picker(projects, config)
 }
 
 /// Run the interactive list TUI. Returns a path if the user chose "cd to project".
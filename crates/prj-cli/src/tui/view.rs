@@ -47,20 +47,37 @@ pub fn render_picker(f: &mut Frame, state: &PickerState, projects: &[Project]) {
                 Style::default()
             };
             let prefix = if i == state.selected { "> " } else { "  " };
-            let mut spans = vec![
-                Span::styled(prefix, style),
-                Span::styled(&project.name, style),
-                Span::styled(
-                    format!("  {}", project.path.display()),
-                    Style::default().fg(Color::DarkGray),
-                ),
-            ];
+            let mut spans = vec![Span::styled(prefix, style)];
+            if project.worktree_of.is_some() {
+                spans.push(Span::styled("└ ", Style::default().fg(Color::DarkGray)));
+            }
+            spans.push(Span::styled(&project.name, style));
+            if let Some(branch) = state
+                .worktree_branches
+                .get(fm.index)
+                .and_then(|b| b.as_ref())
+            {
+                spans.push(Span::styled(
+                    format!(" ({branch})"),
+                    Style::default().fg(Color::Green),
+                ));
+            }
+            spans.push(Span::styled(
+                format!("  {}", project.path.display()),
+                Style::default().fg(Color::DarkGray),
+            ));
             if !project.tags.is_empty() {
                 spans.push(Span::styled(
                     format!("  [{}]", project.tags.join(", ")),
                     Style::default().fg(Color::Magenta),
                 ));
             }
+            if let Some(description) = &project.description {
+                spans.push(Span::styled(
+                    format!("  {description}"),
+                    Style::default().fg(Color::Gray),
+                ));
+            }
             ListItem::new(Line::from(spans))
         })
         .collect();
@@ -116,8 +133,10 @@ pub fn render_list(f: &mut Frame, state: &TuiListState, projects: &[Project]) {
             };
 
             let status_str =
-                if let Some(git_status) = &state.git_statuses.get(i).and_then(|s| s.as_ref()) {
-                    if git_status.is_dirty {
+                if let Some(vcs_status) = &state.vcs_statuses.get(i).and_then(|s| s.as_ref()) {
+                    if vcs_status.needs_attention() {
+                        "attention"
+                    } else if vcs_status.is_dirty {
                         "dirty"
                     } else {
                         "clean"
@@ -134,11 +153,34 @@ pub fn render_list(f: &mut Frame, state: &TuiListState, projects: &[Project]) {
                 Style::default()
             };
 
+            let last_commit = state
+                .last_commits
+                .get(i)
+                .copied()
+                .flatten()
+                .map(|at| crate::cli::format_age(chrono::Utc::now() - at))
+                .unwrap_or_else(|| "-".to_string());
+
+            // Worktrees follow their main repository, see `group_worktrees`
+            let name = if p.worktree_of.is_some() {
+                let branch = state
+                    .vcs_statuses
+                    .get(i)
+                    .and_then(|s| s.as_ref())
+                    .and_then(|s| s.branch.as_deref())
+                    .unwrap_or("detached");
+                format!("└ {} ({branch})", p.name)
+            } else {
+                p.name.clone()
+            };
+
             Row::new(vec![
-                Cell::from(p.name.clone()),
+                Cell::from(name),
                 Cell::from(format!("{}", p.path.display())),
                 Cell::from(if vcs.is_empty() { "-".to_string() } else { vcs }),
                 Cell::from(if bs.is_empty() { "-".to_string() } else { bs }),
+                Cell::from(p.language.clone().unwrap_or_else(|| "-".to_string())),
+                Cell::from(last_commit),
                 Cell::from(tags),
                 Cell::from(status_str.to_string()),
             ])
@@ -150,15 +192,20 @@ pub fn render_list(f: &mut Frame, state: &TuiListState, projects: &[Project]) {
         rows,
         [
             Constraint::Percentage(15),
-            Constraint::Percentage(30),
-            Constraint::Percentage(8),
-            Constraint::Percentage(12),
-            Constraint::Percentage(15),
+            Constraint::Percentage(24),
+            Constraint::Percentage(7),
             Constraint::Percentage(10),
+            Constraint::Percentage(9),
+            Constraint::Percentage(8),
+            Constraint::Percentage(11),
+            Constraint::Percentage(9),
         ],
     )
     .header(
-        Row::new(vec!["Name", "Path", "VCS", "Build", "Tags", "Status"]).style(
+        Row::new(vec![
+            "Name", "Path", "VCS", "Build", "Lang", "Commit", "Tags", "Status",
+        ])
+        .style(
             Style::default()
                 .fg(Color::Cyan)
                 .add_modifier(Modifier::BOLD),
@@ -286,29 +333,84 @@ fn render_stats_view(f: &mut Frame, stats: &prj_core::stats::ProjectStats) {
 
     let mut lines = Vec::new();
 
-    // Git info
-    if let Some(git) = &stats.git {
-        let branch = git.branch.as_deref().unwrap_or("(detached)");
-        let status = if git.is_dirty { "dirty" } else { "clean" };
+    // Description and metadata
+    if let Some(description) = &stats.description {
+        lines.push(Line::from(description.clone()));
+    }
+    for (key, value) in &stats.metadata {
+        lines.push(Line::from(vec![
+            Span::styled(format!("{key}: "), Style::default().fg(Color::Cyan)),
+            Span::raw(value.clone()),
+        ]));
+    }
+    if stats.description.is_some() || !stats.metadata.is_empty() {
+        lines.push(Line::from(""));
+    }
+
+    // VCS info
+    if let Some(vcs) = &stats.vcs {
+        let branch = vcs.branch.as_deref().unwrap_or("(detached)");
+        let status = if vcs.is_dirty { "dirty" } else { "clean" };
         lines.push(Line::from(vec![
-            Span::styled("Git: ", Style::default().fg(Color::Cyan)),
+            Span::styled(format!("{}: ", vcs.vcs), Style::default().fg(Color::Cyan)),
             Span::raw(format!("{branch} ({status})")),
         ]));
-        if git.is_dirty {
+        if vcs.is_dirty {
             lines.push(Line::from(format!(
                 "  changed: {}, staged: {}, untracked: {}",
-                git.changed, git.staged, git.untracked
+                vcs.changed, vcs.staged, vcs.untracked
             )));
         }
-        if git.ahead > 0 || git.behind > 0 {
+        if vcs.ahead > 0 || vcs.behind > 0 {
             lines.push(Line::from(format!(
                 "  ahead: {}, behind: {}",
-                git.ahead, git.behind
+                vcs.ahead, vcs.behind
+            )));
+        }
+        for sm in &vcs.submodules {
+            lines.push(Line::from(Span::styled(
+                format!("  submodule {}: {}", sm.path, sm.state),
+                Style::default().fg(Color::Yellow),
             )));
         }
         lines.push(Line::from(""));
     }
 
+    // Commit activity
+    if let Some(a) = &stats.activity {
+        lines.push(Line::from(vec![
+            Span::styled("last commit: ", Style::default().fg(Color::Cyan)),
+            Span::raw(format!(
+                "{} ago by {}",
+                crate::cli::format_age(chrono::Utc::now() - a.last_commit),
+                a.last_author
+            )),
+        ]));
+        lines.push(Line::from(format!(
+            "  commits: {} in 7d, {} in 30d, {} in 90d",
+            a.commits_7d, a.commits_30d, a.commits_90d
+        )));
+        lines.push(Line::from(format!(
+            "  {}{} commits by {} authors since {}",
+            a.total_commits,
+            if a.truncated { "+" } else { "" },
+            a.authors,
+            a.first_commit
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d")
+        )));
+        lines.push(Line::from(""));
+    }
+
+    // Pinned toolchains
+    if !stats.toolchains.is_empty() {
+        lines.push(Line::from(vec![
+            Span::styled("toolchains: ", Style::default().fg(Color::Cyan)),
+            Span::raw(prj_core::toolchain::toolchain_list(&stats.toolchains).join(", ")),
+        ]));
+        lines.push(Line::from(""));
+    }
+
     // LOC
     lines.push(Line::from(vec![
         Span::styled("Lines of Code: ", Style::default().fg(Color::Cyan)),
@@ -0,0 +1,278 @@
+//! Artifact directories discovered from a project's real build configuration.
+//!
+//! The static table in [`crate::detect`] only knows default names such as
+//! `target` and `build`. This module adds what the project is actually
+//! configured to use: Cargo's `CARGO_TARGET_DIR` and `build.target-dir`,
+//! a custom Gradle build directory, and build trees recognised by their
+//! fingerprint files (`CMakeCache.txt`, `meson-private/`, `CACHEDIR.TAG`).
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::detect::BuildSystem;
+
+/// Files or directories that mark a directory as a build tree.
+const FINGERPRINTS: &[&str] = &["CMakeCache.txt", "meson-private", "CACHEDIR.TAG"];
+
+/// Directories not searched for build trees.
+const SKIP_DIRS: &[&str] = &[".git", ".hg", ".jj", ".svn", "src", "node_modules"];
+
+/// Artifact directories found from build configuration, relative to the
+/// project where possible.
+#[derive(Debug, Default)]
+pub struct DiscoveredArtifacts {
+    /// Directories inside the project, safe to clean.
+    pub local: Vec<String>,
+    /// Directories that resolve outside the project, such as a
+    /// `CARGO_TARGET_DIR` shared by several projects. Never cleaned.
+    pub shared: Vec<String>,
+}
+
+impl DiscoveredArtifacts {
+    fn push(&mut self, project: &Path, dir: String) {
+        let list = if is_shared(project, &dir) {
+            &mut self.shared
+        } else {
+            &mut self.local
+        };
+        if !list.contains(&dir) {
+            list.push(dir);
+        }
+    }
+}
+
+/// Find configured and fingerprinted artifact directories for a project
+/// using `build_systems`.
+pub fn discover(project: &Path, build_systems: &[BuildSystem]) -> DiscoveredArtifacts {
+    let mut found = DiscoveredArtifacts::default();
+
+    if build_systems.contains(&BuildSystem::Cargo) {
+        // The environment at scan time says nothing about later builds, so
+        // `CARGO_TARGET_DIR` is only ever listed as shared.
+        if let Some(dir) = std::env::var_os("CARGO_TARGET_DIR") {
+            let dir = normalize(&project.join(dir));
+            found.shared.push(dir.to_string_lossy().to_string());
+        }
+        if let Some(dir) = cargo_target_dir(project) {
+            found.push(project, dir);
+        }
+    }
+    if build_systems.contains(&BuildSystem::Gradle)
+        && let Some(dir) = gradle_build_dir(project)
+    {
+        found.push(project, dir);
+    }
+    for dir in fingerprinted_dirs(project) {
+        found.push(project, dir);
+    }
+
+    found
+}
+
+/// Returns true if `dir` (relative to `project`, or absolute) resolves to a
+/// location outside `project`. Symlinks are followed when the directory
+/// exists.
+pub fn is_shared(project: &Path, dir: &str) -> bool {
+    let joined = project.join(dir);
+    let resolved = joined.canonicalize().unwrap_or_else(|_| normalize(&joined));
+    let project = project
+        .canonicalize()
+        .unwrap_or_else(|_| normalize(project));
+    !resolved.starts_with(&project) || resolved == project
+}
+
+/// Lexically resolve `.` and `..` components.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Express `dir` relative to `project` when it lies inside it, otherwise
+/// keep it absolute.
+fn relative_to(project: &Path, dir: &Path) -> String {
+    let dir = normalize(dir);
+    dir.strip_prefix(project)
+        .map(Path::to_path_buf)
+        .unwrap_or(dir)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Cargo's configured target directory: `build.target-dir` from the
+/// nearest `.cargo/config.toml` in the project or its ancestors. A
+/// relative `target-dir` is relative to the directory holding `.cargo`.
+fn cargo_target_dir(project: &Path) -> Option<String> {
+    for base in project.ancestors() {
+        for name in ["config.toml", "config"] {
+            let Ok(content) = std::fs::read_to_string(base.join(".cargo").join(name)) else {
+                continue;
+            };
+            let Ok(config) = content.parse::<toml::Table>() else {
+                continue;
+            };
+            if let Some(dir) = config
+                .get("build")
+                .and_then(|b| b.get("target-dir"))
+                .and_then(|d| d.as_str())
+            {
+                return Some(relative_to(project, &base.join(dir)));
+            }
+        }
+    }
+    None
+}
+
+/// A `buildDir` or `layout.buildDirectory` override in the Gradle build
+/// script. See [`parse_gradle_build_dir`] for the forms understood.
+fn gradle_build_dir(project: &Path) -> Option<String> {
+    ["build.gradle.kts", "build.gradle"]
+        .iter()
+        .filter_map(|name| std::fs::read_to_string(project.join(name)).ok())
+        .flat_map(|content| {
+            content
+                .lines()
+                .filter_map(parse_gradle_build_dir)
+                .collect::<Vec<_>>()
+        })
+        .next()
+        .map(|dir| relative_to(project, &project.join(dir)))
+}
+
+/// The build directory set by one line of a Gradle script, if the line is
+/// one of these assignments with a literal path:
+///
+/// - `buildDir = "out"`
+/// - `layout.buildDirectory.set(file("out"))`
+/// - `layout.buildDirectory.set(layout.projectDirectory.dir("out"))`
+///
+/// each optionally prefixed with `project.`. Anything else, including
+/// interpolated strings, is ignored: the result decides what gets deleted.
+fn parse_gradle_build_dir(line: &str) -> Option<String> {
+    let line = line.trim().trim_end_matches(';').trim_end();
+    let line = line.strip_prefix("project.").unwrap_or(line);
+
+    if let Some(rest) = line.strip_prefix("buildDir") {
+        let value = rest.trim_start().strip_prefix('=')?.trim();
+        return quoted(value).or_else(|| call_arg(value, "file"));
+    }
+
+    let arg = line
+        .strip_prefix("layout.buildDirectory.set(")?
+        .strip_suffix(')')?
+        .trim();
+    call_arg(arg, "file").or_else(|| {
+        [
+            "layout.projectDirectory.dir",
+            "project.layout.projectDirectory.dir",
+        ]
+        .iter()
+        .find_map(|call| call_arg(arg, call))
+    })
+}
+
+/// The literal argument of `call("...")`.
+fn call_arg(expr: &str, call: &str) -> Option<String> {
+    let arg = expr
+        .strip_prefix(call)?
+        .strip_prefix('(')?
+        .strip_suffix(')')?;
+    quoted(arg.trim())
+}
+
+/// The contents of a single- or double-quoted string literal without
+/// escapes or `$` interpolation.
+fn quoted(value: &str) -> Option<String> {
+    let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+    let inner = value[1..].strip_suffix(quote)?;
+    if inner.is_empty() || inner.contains([quote, '\\', '$']) {
+        return None;
+    }
+    Some(inner.to_string())
+}
+
+/// Subdirectories up to three levels deep that carry a build-tree
+/// fingerprint, e.g. `cmake-build-debug` or `out/build/x64-Debug`.
+fn fingerprinted_dirs(project: &Path) -> Vec<String> {
+    let mut found = Vec::new();
+    let walker = walkdir::WalkDir::new(project)
+        .min_depth(1)
+        .max_depth(3)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| {
+            e.file_type().is_dir() && !SKIP_DIRS.contains(&e.file_name().to_string_lossy().as_ref())
+        });
+    for entry in walker.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if found.iter().any(|f: &PathBuf| path.starts_with(f)) {
+            continue;
+        }
+        if FINGERPRINTS.iter().any(|f| path.join(f).exists()) {
+            found.push(path.to_path_buf());
+        }
+    }
+    found.iter().map(|dir| relative_to(project, dir)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_gradle_build_dir as parse;
+
+    #[test]
+    fn gradle_build_dir_assignments() {
+        assert_eq!(parse(r#"buildDir = "out""#).as_deref(), Some("out"));
+        assert_eq!(
+            parse("  buildDir = 'out/gradle'").as_deref(),
+            Some("out/gradle")
+        );
+        assert_eq!(
+            parse(r#"project.buildDir = "out";"#).as_deref(),
+            Some("out")
+        );
+        assert_eq!(parse(r#"buildDir = file("out")"#).as_deref(), Some("out"));
+        assert_eq!(
+            parse(r#"layout.buildDirectory.set(file("out"))"#).as_deref(),
+            Some("out")
+        );
+        assert_eq!(
+            parse(r#"layout.buildDirectory.set(layout.projectDirectory.dir("out"))"#).as_deref(),
+            Some("out")
+        );
+        assert_eq!(
+            parse(
+                r#"project.layout.buildDirectory.set(project.layout.projectDirectory.dir('out'))"#
+            )
+            .as_deref(),
+            Some("out")
+        );
+    }
+
+    #[test]
+    fn gradle_build_dir_ignores_other_lines() {
+        for line in [
+            r#"// buildDir = "out""#,
+            r#"println("buildDir is $buildDir")"#,
+            r#"val dir = buildDir.resolve("out")"#,
+            r#"buildDir = "$rootDir/out""#,
+            r#"buildDir = "${project.name}-out""#,
+            r#"buildDir = """#,
+            r#"buildDir = "out" + suffix"#,
+            r#"buildDir = "out"  // comment"#,
+            r#"buildDirectory = "out""#,
+            r#"layout.buildDirectory.set(file(rootDir))"#,
+            r#"layout.buildDirectory.set(file("out").parentFile)"#,
+            r#"layout.buildDirectory.set(rootProject.layout.buildDirectory.dir("out"))"#,
+            r#"tasks.register("clean") { delete(layout.buildDirectory) }"#,
+        ] {
+            assert_eq!(parse(line), None, "{line}");
+        }
+    }
+}
@@ -2,22 +2,32 @@ use std::path::Path;
 
 use walkdir::WalkDir;
 
+use crate::artifacts;
+
 /// Summary of artifact directories that would be removed by a clean operation.
 pub struct CleanPreview {
     pub dirs: Vec<(String, u64)>,
     pub total_bytes: u64,
+    /// Existing directories left alone because they resolve outside the project.
+    pub skipped_shared: Vec<String>,
 }
 
 /// Preview what would be cleaned for a project.
 pub fn preview_clean(project_path: &Path, artifact_dirs: &[String]) -> CleanPreview {
     let mut dirs = Vec::new();
     let mut total_bytes = 0;
+    let mut skipped_shared = Vec::new();
 
     for dir_name in artifact_dirs {
         let dir_path = project_path.join(dir_name);
         if !dir_path.exists() {
             continue;
         }
+        // Never delete through a symlink or `..` into another project
+        if artifacts::is_shared(project_path, dir_name) {
+            skipped_shared.push(dir_name.clone());
+            continue;
+        }
         let mut size = 0u64;
         for entry in WalkDir::new(&dir_path)
             .follow_links(false)
@@ -32,7 +42,11 @@ pub fn preview_clean(project_path: &Path, artifact_dirs: &[String]) -> CleanPrev
         total_bytes += size;
     }
 
-    CleanPreview { dirs, total_bytes }
+    CleanPreview {
+        dirs,
+        total_bytes,
+        skipped_shared,
+    }
 }
 
 /// Delete artifact directories, returning total bytes freed.
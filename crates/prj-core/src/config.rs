@@ -1,8 +1,13 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 
+use crate::detect::CustomBuildSystem;
+use crate::scan::ScanOptions;
+use crate::storage::StorageBackend;
+
 /// User configuration loaded from `~/.config/prj/config.toml`.
 ///
 /// All fields have sensible defaults so the config file is optional.
@@ -12,7 +17,32 @@ pub struct Config {
     pub shell_cmd: String,
     #[serde(default = "default_scan_depth")]
     pub scan_depth: usize,
+    /// Gitignore-style globs for directories `prj scan` never enters.
+    #[serde(default)]
+    pub scan_exclude: Vec<String>,
+    /// Hidden directory names `prj scan` descends into anyway.
+    #[serde(default)]
+    pub scan_include_hidden: Vec<String>,
+    #[serde(default)]
+    pub scan_follow_symlinks: bool,
+    /// Honour `.gitignore` files while scanning (`.prjignore` always applies).
+    #[serde(default = "default_scan_gitignore")]
+    pub scan_gitignore: bool,
     pub database_path: Option<PathBuf>,
+    /// Which backend stores the project database.
+    #[serde(default)]
+    pub storage: StorageBackend,
+    #[serde(default = "default_lock_timeout_secs")]
+    pub lock_timeout_secs: u64,
+    /// Blend visit frecency into the picker's ranking.
+    #[serde(default = "default_frecency")]
+    pub frecency: bool,
+    /// How many operations `prj undo` can revert.
+    #[serde(default = "default_history_limit")]
+    pub history_limit: usize,
+    /// Extra build systems, from `[[build_system]]` tables.
+    #[serde(default, rename = "build_system")]
+    pub build_systems: Vec<CustomBuildSystem>,
 }
 
 fn default_shell_cmd() -> String {
@@ -23,12 +53,37 @@ fn default_scan_depth() -> usize {
     3
 }
 
+fn default_scan_gitignore() -> bool {
+    true
+}
+
+fn default_lock_timeout_secs() -> u64 {
+    10
+}
+
+fn default_frecency() -> bool {
+    true
+}
+
+fn default_history_limit() -> usize {
+    50
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             shell_cmd: default_shell_cmd(),
             scan_depth: default_scan_depth(),
+            scan_exclude: Vec::new(),
+            scan_include_hidden: Vec::new(),
+            scan_follow_symlinks: false,
+            scan_gitignore: default_scan_gitignore(),
             database_path: None,
+            storage: StorageBackend::default(),
+            lock_timeout_secs: default_lock_timeout_secs(),
+            frecency: default_frecency(),
+            history_limit: default_history_limit(),
+            build_systems: Vec::new(),
         }
     }
 }
@@ -51,7 +106,30 @@ impl Config {
     pub fn database_path(&self) -> PathBuf {
         self.database_path
             .clone()
-            .unwrap_or_else(|| Self::project_dirs().data_dir().join("projects.toml"))
+            .unwrap_or_else(|| Self::default_database_path(self.storage))
+    }
+
+    /// Database location for a backend when `database_path` is not set.
+    pub fn default_database_path(backend: StorageBackend) -> PathBuf {
+        Self::project_dirs()
+            .data_dir()
+            .join(backend.default_file_name())
+    }
+
+    /// Scan options from the config, before command-line overrides.
+    pub fn scan_options(&self) -> ScanOptions {
+        ScanOptions {
+            max_depth: self.scan_depth,
+            exclude: self.scan_exclude.clone(),
+            include_hidden: self.scan_include_hidden.clone(),
+            follow_symlinks: self.scan_follow_symlinks,
+            respect_gitignore: self.scan_gitignore,
+        }
+    }
+
+    /// How long to wait for another `prj` process to release the database.
+    pub fn lock_timeout(&self) -> Duration {
+        Duration::from_secs(self.lock_timeout_secs)
     }
 
     fn project_dirs() -> ProjectDirs {
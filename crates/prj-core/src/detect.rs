@@ -1,34 +1,133 @@
-use std::path::Path;
+use std::cell::OnceCell;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
 use serde::{Deserialize, Serialize};
 
+use crate::artifacts;
+use crate::language;
+use crate::toolchain::{self, Toolchains};
+use crate::worktree;
+
 /// Version control systems that `prj` can detect.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum VcsType {
     Git,
+    Mercurial,
+    Jujutsu,
+    Subversion,
+    Fossil,
 }
 
-impl std::fmt::Display for VcsType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl VcsType {
+    /// Detection order. Jujutsu comes before Git so that a colocated `jj`
+    /// repository reports `jj` status rather than its detached git HEAD.
+    pub const ALL: [VcsType; 5] = [
+        VcsType::Jujutsu,
+        VcsType::Git,
+        VcsType::Mercurial,
+        VcsType::Subversion,
+        VcsType::Fossil,
+    ];
+
+    /// Entries that mark a working copy of this VCS.
+    fn markers(self) -> &'static [&'static str] {
         match self {
-            VcsType::Git => write!(f, "Git"),
+            VcsType::Git => &[".git"],
+            VcsType::Mercurial => &[".hg"],
+            VcsType::Jujutsu => &[".jj"],
+            VcsType::Subversion => &[".svn"],
+            VcsType::Fossil => &[".fslckout", "_FOSSIL_"],
         }
     }
+
+    /// Returns true if `path` is the root of a working copy of this VCS.
+    pub fn detected_in(self, path: &Path) -> bool {
+        self.markers().iter().any(|m| path.join(m).exists())
+    }
+}
+
+impl std::fmt::Display for VcsType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            VcsType::Git => "Git",
+            VcsType::Mercurial => "Mercurial",
+            VcsType::Jujutsu => "Jujutsu",
+            VcsType::Subversion => "Subversion",
+            VcsType::Fossil => "Fossil",
+        };
+        write!(f, "{s}")
+    }
 }
 
 /// Build systems detected by the presence of their marker files.
+///
+/// Stored by name. Names that aren't built in become [`BuildSystem::Custom`],
+/// so build systems declared in the config survive a database round-trip.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum BuildSystem {
     Cargo,
     Npm,
     CMake,
     Go,
     Python,
+    Poetry,
+    Hatch,
+    Uv,
     Zig,
     Make,
     Gradle,
     Maven,
     Meson,
+    DotNet,
+    Cabal,
+    Stack,
+    SwiftPm,
+    Mix,
+    Dart,
+    Flutter,
+    Bazel,
+    Deno,
+    /// A build system declared with `[[build_system]]` in the config.
+    Custom(String),
+}
+
+impl From<String> for BuildSystem {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "Cargo" => BuildSystem::Cargo,
+            "Npm" => BuildSystem::Npm,
+            "CMake" => BuildSystem::CMake,
+            "Go" => BuildSystem::Go,
+            "Python" => BuildSystem::Python,
+            "Poetry" => BuildSystem::Poetry,
+            "Hatch" => BuildSystem::Hatch,
+            "Uv" => BuildSystem::Uv,
+            "Zig" => BuildSystem::Zig,
+            "Make" => BuildSystem::Make,
+            "Gradle" => BuildSystem::Gradle,
+            "Maven" => BuildSystem::Maven,
+            "Meson" => BuildSystem::Meson,
+            "DotNet" => BuildSystem::DotNet,
+            "Cabal" => BuildSystem::Cabal,
+            "Stack" => BuildSystem::Stack,
+            "SwiftPm" => BuildSystem::SwiftPm,
+            "Mix" => BuildSystem::Mix,
+            "Dart" => BuildSystem::Dart,
+            "Flutter" => BuildSystem::Flutter,
+            "Bazel" => BuildSystem::Bazel,
+            "Deno" => BuildSystem::Deno,
+            _ => BuildSystem::Custom(name),
+        }
+    }
+}
+
+impl From<BuildSystem> for String {
+    fn from(system: BuildSystem) -> Self {
+        system.to_string()
+    }
 }
 
 impl std::fmt::Display for BuildSystem {
@@ -39,80 +138,267 @@ impl std::fmt::Display for BuildSystem {
             BuildSystem::CMake => "CMake",
             BuildSystem::Go => "Go",
             BuildSystem::Python => "Python",
+            BuildSystem::Poetry => "Poetry",
+            BuildSystem::Hatch => "Hatch",
+            BuildSystem::Uv => "Uv",
             BuildSystem::Zig => "Zig",
             BuildSystem::Make => "Make",
             BuildSystem::Gradle => "Gradle",
             BuildSystem::Maven => "Maven",
             BuildSystem::Meson => "Meson",
+            BuildSystem::DotNet => "DotNet",
+            BuildSystem::Cabal => "Cabal",
+            BuildSystem::Stack => "Stack",
+            BuildSystem::SwiftPm => "SwiftPm",
+            BuildSystem::Mix => "Mix",
+            BuildSystem::Dart => "Dart",
+            BuildSystem::Flutter => "Flutter",
+            BuildSystem::Bazel => "Bazel",
+            BuildSystem::Deno => "Deno",
+            BuildSystem::Custom(name) => name,
         };
         write!(f, "{s}")
     }
 }
 
+/// A built-in marker: a file name or glob, optionally required to contain
+/// some text.
+struct MarkerSpec {
+    pattern: &'static str,
+    contains: Option<&'static str>,
+}
+
+const fn file(pattern: &'static str) -> MarkerSpec {
+    MarkerSpec {
+        pattern,
+        contains: None,
+    }
+}
+
+const fn file_containing(pattern: &'static str, text: &'static str) -> MarkerSpec {
+    MarkerSpec {
+        pattern,
+        contains: Some(text),
+    }
+}
+
 struct BuildSystemInfo {
-    marker: &'static str,
+    markers: &'static [MarkerSpec],
     system: BuildSystem,
     artifact_dirs: &'static [&'static str],
 }
 
 const BUILD_SYSTEMS: &[BuildSystemInfo] = &[
     BuildSystemInfo {
-        marker: "Cargo.toml",
+        markers: &[file("Cargo.toml")],
         system: BuildSystem::Cargo,
         artifact_dirs: &["target"],
     },
     BuildSystemInfo {
-        marker: "package.json",
+        markers: &[file("package.json")],
         system: BuildSystem::Npm,
         artifact_dirs: &["node_modules", "dist", "build"],
     },
     BuildSystemInfo {
-        marker: "CMakeLists.txt",
+        markers: &[file("CMakeLists.txt")],
         system: BuildSystem::CMake,
         artifact_dirs: &["build"],
     },
     BuildSystemInfo {
-        marker: "go.mod",
+        markers: &[file("go.mod"), file("go.work")],
         system: BuildSystem::Go,
         artifact_dirs: &[],
     },
     BuildSystemInfo {
-        marker: "pyproject.toml",
+        markers: &[file("pyproject.toml")],
         system: BuildSystem::Python,
         artifact_dirs: &["__pycache__", ".venv", "dist"],
     },
     BuildSystemInfo {
-        marker: "build.zig",
-        system: BuildSystem::Zig,
-        artifact_dirs: &["zig-out", "zig-cache"],
+        markers: &[
+            file("poetry.lock"),
+            file_containing("pyproject.toml", "[tool.poetry"),
+        ],
+        system: BuildSystem::Poetry,
+        artifact_dirs: &[],
     },
     BuildSystemInfo {
-        marker: "Makefile",
-        system: BuildSystem::Make,
+        markers: &[
+            file("hatch.toml"),
+            file_containing("pyproject.toml", "[tool.hatch"),
+        ],
+        system: BuildSystem::Hatch,
         artifact_dirs: &[],
     },
     BuildSystemInfo {
-        marker: "build.gradle",
-        system: BuildSystem::Gradle,
-        artifact_dirs: &["build", ".gradle"],
+        markers: &[
+            file("uv.lock"),
+            file_containing("pyproject.toml", "[tool.uv"),
+        ],
+        system: BuildSystem::Uv,
+        artifact_dirs: &[".venv"],
     },
     BuildSystemInfo {
-        marker: "build.gradle.kts",
+        markers: &[file("build.zig")],
+        system: BuildSystem::Zig,
+        artifact_dirs: &["zig-out", "zig-cache", ".zig-cache"],
+    },
+    BuildSystemInfo {
+        markers: &[file("Makefile")],
+        system: BuildSystem::Make,
+        artifact_dirs: &[],
+    },
+    BuildSystemInfo {
+        markers: &[file("build.gradle"), file("build.gradle.kts")],
         system: BuildSystem::Gradle,
         artifact_dirs: &["build", ".gradle"],
     },
     BuildSystemInfo {
-        marker: "pom.xml",
+        markers: &[file("pom.xml")],
         system: BuildSystem::Maven,
         artifact_dirs: &["target"],
     },
     BuildSystemInfo {
-        marker: "meson.build",
+        markers: &[file("meson.build")],
         system: BuildSystem::Meson,
         artifact_dirs: &["builddir"],
     },
+    BuildSystemInfo {
+        markers: &[
+            file("*.sln"),
+            file("*.csproj"),
+            file("*.fsproj"),
+            file("*.vbproj"),
+        ],
+        system: BuildSystem::DotNet,
+        artifact_dirs: &["bin", "obj"],
+    },
+    BuildSystemInfo {
+        markers: &[file("*.cabal"), file("cabal.project")],
+        system: BuildSystem::Cabal,
+        artifact_dirs: &["dist-newstyle"],
+    },
+    BuildSystemInfo {
+        markers: &[file("stack.yaml")],
+        system: BuildSystem::Stack,
+        artifact_dirs: &[".stack-work"],
+    },
+    BuildSystemInfo {
+        markers: &[file("Package.swift"), file("Package@swift-*.swift")],
+        system: BuildSystem::SwiftPm,
+        artifact_dirs: &[".build"],
+    },
+    BuildSystemInfo {
+        markers: &[file("mix.exs")],
+        system: BuildSystem::Mix,
+        artifact_dirs: &["_build", "deps"],
+    },
+    BuildSystemInfo {
+        markers: &[file("pubspec.yaml")],
+        system: BuildSystem::Dart,
+        artifact_dirs: &[".dart_tool", "build"],
+    },
+    BuildSystemInfo {
+        markers: &[file_containing("pubspec.yaml", "sdk: flutter")],
+        system: BuildSystem::Flutter,
+        artifact_dirs: &[],
+    },
+    BuildSystemInfo {
+        markers: &[
+            file("MODULE.bazel"),
+            file("WORKSPACE"),
+            file("WORKSPACE.bazel"),
+        ],
+        system: BuildSystem::Bazel,
+        artifact_dirs: &["bazel-bin", "bazel-out", "bazel-testlogs"],
+    },
+    BuildSystemInfo {
+        markers: &[file("deno.json"), file("deno.jsonc")],
+        system: BuildSystem::Deno,
+        artifact_dirs: &[],
+    },
 ];
 
+/// A marker file in a `[[build_system]]` config entry.
+///
+/// Written either as a plain file name or glob (`"*.csproj"`), or as a table
+/// that also requires the file to contain some text:
+///
+/// ```toml
+/// markers = ["BUILD.mk", { file = "Cargo.toml", contains = "[workspace]" }]
+/// ```
+///
+/// Globs are compiled when the config is read; an invalid glob is a config
+/// error.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "MarkerRepr")]
+pub struct Marker {
+    pub file: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contains: Option<String>,
+    #[serde(skip)]
+    glob: Option<GlobMatcher>,
+}
+
+impl Marker {
+    /// A marker for `file`, which may be a glob.
+    pub fn new(file: String, contains: Option<String>) -> Result<Self, globset::Error> {
+        let glob = if is_glob(&file) {
+            Some(Glob::new(&file)?.compile_matcher())
+        } else {
+            None
+        };
+        Ok(Self {
+            file,
+            contains,
+            glob,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MarkerRepr {
+    File(String),
+    Table {
+        file: String,
+        #[serde(default)]
+        contains: Option<String>,
+    },
+}
+
+impl TryFrom<MarkerRepr> for Marker {
+    type Error = String;
+
+    fn try_from(repr: MarkerRepr) -> Result<Self, Self::Error> {
+        let (file, contains) = match repr {
+            MarkerRepr::File(file) => (file, None),
+            MarkerRepr::Table { file, contains } => (file, contains),
+        };
+        Marker::new(file, contains).map_err(|e| format!("invalid marker glob: {e}"))
+    }
+}
+
+/// A build system declared in `config.toml`:
+///
+/// ```toml
+/// [[build_system]]
+/// name = "Buck2"
+/// markers = [".buckconfig"]
+/// artifact_dirs = ["buck-out"]
+/// ```
+///
+/// A directory containing any of the markers is detected as this build
+/// system. Using a built-in name such as `Cargo` adds markers and artifact
+/// directories to that built-in instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomBuildSystem {
+    pub name: String,
+    pub markers: Vec<Marker>,
+    #[serde(default)]
+    pub artifact_dirs: Vec<String>,
+}
+
 /// Known artifact directory names (used during scan to skip).
 pub const ARTIFACT_DIR_NAMES: &[&str] = &[
     "target",
@@ -125,116 +411,222 @@ pub const ARTIFACT_DIR_NAMES: &[&str] = &[
     "zig-cache",
     ".gradle",
     "builddir",
+    "obj",
+    "dist-newstyle",
+    "_build",
+    "bazel-bin",
+    "bazel-out",
+    "bazel-testlogs",
     ".git",
 ];
 
-/// Result of scanning a project directory for VCS and build system markers.
-pub struct DetectionResult {
-    pub vcs: Vec<VcsType>,
-    pub build_systems: Vec<BuildSystem>,
-    pub artifact_dirs: Vec<String>,
+/// Returns true if a marker pattern has glob characters.
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
 }
 
-/// Detect VCS, build systems, and artifact directories for a given path.
-pub fn detect_project(path: &Path) -> DetectionResult {
-    let mut vcs = Vec::new();
-    let mut build_systems = Vec::new();
-    let mut artifact_dirs = Vec::new();
+/// The glob markers of [`BUILD_SYSTEMS`], compiled into one set on first
+/// use, with the pattern of each glob in the set.
+static BUILTIN_GLOBS: LazyLock<(GlobSet, Vec<&'static str>)> = LazyLock::new(|| {
+    let patterns: Vec<&'static str> = BUILD_SYSTEMS
+        .iter()
+        .flat_map(|info| info.markers)
+        .map(|m| m.pattern)
+        .filter(|p| is_glob(p))
+        .collect();
+    let mut builder = GlobSetBuilder::new();
+    for pattern in &patterns {
+        builder.add(Glob::new(pattern).expect("built-in marker globs are valid"));
+    }
+    let set = builder.build().expect("built-in marker globs are valid");
+    (set, patterns)
+});
 
-    // VCS detection
-    if path.join(".git").exists() {
-        vcs.push(VcsType::Git);
+/// A directory being checked for markers. The file listing is only read
+/// once, and only if a glob marker needs it; it is matched against all
+/// built-in globs in one pass.
+struct MarkerDir<'a> {
+    path: &'a Path,
+    entries: OnceCell<Vec<String>>,
+    /// For each glob in [`BUILTIN_GLOBS`], the entries it matches.
+    builtin_matches: OnceCell<Vec<Vec<usize>>>,
+}
+
+impl<'a> MarkerDir<'a> {
+    fn new(path: &'a Path) -> Self {
+        Self {
+            path,
+            entries: OnceCell::new(),
+            builtin_matches: OnceCell::new(),
+        }
     }
 
-    // Build system detection
-    for info in BUILD_SYSTEMS {
-        if path.join(info.marker).exists() {
-            // Avoid duplicate build systems (e.g. build.gradle and build.gradle.kts)
-            if !build_systems.contains(&info.system) {
-                build_systems.push(info.system.clone());
-            }
-            for dir in info.artifact_dirs {
-                let s = dir.to_string();
-                if !artifact_dirs.contains(&s) {
-                    artifact_dirs.push(s);
+    /// Whether the file `name` contains `contains` (if given).
+    fn has_text(&self, name: &str, contains: Option<&str>) -> bool {
+        match contains {
+            None => true,
+            Some(text) => std::fs::read_to_string(self.path.join(name))
+                .is_ok_and(|content| content.contains(text)),
+        }
+    }
+
+    /// Returns true if a built-in marker matches a file (containing
+    /// `contains`, if given).
+    fn matches_builtin(&self, pattern: &str, contains: Option<&str>) -> bool {
+        if !is_glob(pattern) {
+            return self.path.join(pattern).exists() && self.has_text(pattern, contains);
+        }
+        let (set, patterns) = &*BUILTIN_GLOBS;
+        let Some(index) = patterns.iter().position(|p| *p == pattern) else {
+            return false;
+        };
+        let matches = self.builtin_matches.get_or_init(|| {
+            let mut matches = vec![Vec::new(); patterns.len()];
+            for (entry, name) in self.entries().iter().enumerate() {
+                for glob in set.matches(name) {
+                    matches[glob].push(entry);
                 }
             }
+            matches
+        });
+        matches[index]
+            .iter()
+            .any(|&entry| self.has_text(&self.entries()[entry], contains))
+    }
+
+    /// Returns true if a configured marker matches a file (containing its
+    /// text, if given).
+    fn matches_custom(&self, marker: &Marker) -> bool {
+        let contains = marker.contains.as_deref();
+        match &marker.glob {
+            None => self.path.join(&marker.file).exists() && self.has_text(&marker.file, contains),
+            Some(glob) => self
+                .entries()
+                .iter()
+                .any(|name| glob.is_match(name) && self.has_text(name, contains)),
         }
     }
 
-    DetectionResult {
-        vcs,
-        build_systems,
-        artifact_dirs,
+    fn entries(&self) -> &[String] {
+        self.entries.get_or_init(|| {
+            std::fs::read_dir(self.path)
+                .map(|dir| {
+                    dir.filter_map(|e| e.ok())
+                        .filter_map(|e| e.file_name().into_string().ok())
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
     }
 }
 
-/// Returns true if the given path looks like a project root.
-pub fn is_project(path: &Path) -> bool {
-    if path.join(".git").exists() {
-        return true;
-    }
-    for info in BUILD_SYSTEMS {
-        if path.join(info.marker).exists() {
-            return true;
-        }
+impl BuildSystemInfo {
+    fn detected_in(&self, dir: &MarkerDir) -> bool {
+        self.markers
+            .iter()
+            .any(|m| dir.matches_builtin(m.pattern, m.contains))
     }
-    false
 }
 
-/// Scan a directory tree for projects up to `max_depth`.
-/// Skips children of already-detected projects and artifact directories.
-pub fn scan_projects(root: &Path, max_depth: usize) -> Vec<std::path::PathBuf> {
-    let mut found = Vec::new();
-
-    let walker = walkdir::WalkDir::new(root)
-        .max_depth(max_depth)
-        .follow_links(false)
-        .into_iter();
+impl CustomBuildSystem {
+    fn detected_in(&self, dir: &MarkerDir) -> bool {
+        self.markers.iter().any(|m| dir.matches_custom(m))
+    }
+}
 
-    // Track project roots so we skip their children
-    let mut project_roots: Vec<std::path::PathBuf> = Vec::new();
+/// Result of scanning a project directory for VCS and build system markers.
+pub struct DetectionResult {
+    pub vcs: Vec<VcsType>,
+    pub build_systems: Vec<BuildSystem>,
+    pub artifact_dirs: Vec<String>,
+    /// Configured build directories outside the project; never cleaned.
+    pub shared_artifact_dirs: Vec<String>,
+    /// Toolchain versions the project pins (see [`crate::toolchain`]).
+    pub toolchains: Toolchains,
+    /// Main working tree, when this is a linked git worktree.
+    pub worktree_of: Option<PathBuf>,
+    /// Primary language by sampled file size (see [`crate::language`]).
+    pub language: Option<String>,
+}
 
-    for entry in walker.filter_entry(|e| {
-        // Always allow the root itself
-        if e.depth() == 0 {
-            return true;
-        }
-        // Skip non-directories
-        if !e.file_type().is_dir() {
-            return false;
+impl DetectionResult {
+    fn add_build_system<'a>(
+        &mut self,
+        system: BuildSystem,
+        dirs: impl IntoIterator<Item = &'a str>,
+    ) {
+        // A custom entry may extend a built-in build system of the same name
+        if !self.build_systems.contains(&system) {
+            self.build_systems.push(system);
         }
-        // Skip artifact directories
-        if let Some(name) = e.file_name().to_str() {
-            if ARTIFACT_DIR_NAMES.contains(&name) {
-                return false;
-            }
-            // Skip hidden directories (except .git which we handle)
-            if name.starts_with('.') {
-                return false;
+        for dir in dirs {
+            let s = dir.to_string();
+            if !self.artifact_dirs.contains(&s) {
+                self.artifact_dirs.push(s);
             }
         }
-        true
-    }) {
-        let Ok(entry) = entry else { continue };
-        if !entry.file_type().is_dir() {
-            continue;
-        }
-        let path = entry.path();
+    }
+}
 
-        // Skip if this is a child of an already-found project
-        if project_roots
-            .iter()
-            .any(|root| path.starts_with(root) && path != root)
-        {
-            continue;
+/// Detect VCS, build systems, and artifact directories for a given path,
+/// checking the built-in build systems first and then `custom` ones.
+/// Artifact directories from the static table are extended with those
+/// found in the build configuration (see [`crate::artifacts`]).
+pub fn detect_project(path: &Path, custom: &[CustomBuildSystem]) -> DetectionResult {
+    let mut result = DetectionResult {
+        vcs: Vec::new(),
+        build_systems: Vec::new(),
+        artifact_dirs: Vec::new(),
+        shared_artifact_dirs: Vec::new(),
+        toolchains: toolchain::detect_toolchains(path),
+        worktree_of: None,
+        language: None,
+    };
+
+    // VCS detection
+    result.vcs = VcsType::ALL
+        .into_iter()
+        .filter(|vcs| vcs.detected_in(path))
+        .collect();
+
+    // Build system detection
+    let dir = MarkerDir::new(path);
+    for info in BUILD_SYSTEMS {
+        if info.detected_in(&dir) {
+            result.add_build_system(info.system.clone(), info.artifact_dirs.iter().copied());
+        }
+    }
+    for info in custom {
+        if info.detected_in(&dir) {
+            result.add_build_system(
+                BuildSystem::from(info.name.clone()),
+                info.artifact_dirs.iter().map(String::as_str),
+            );
         }
+    }
 
-        if is_project(path) {
-            project_roots.push(path.to_path_buf());
-            found.push(path.to_path_buf());
+    let discovered = artifacts::discover(path, &result.build_systems);
+    for dir in discovered.local {
+        if !result.artifact_dirs.contains(&dir) {
+            result.artifact_dirs.push(dir);
         }
     }
+    result.shared_artifact_dirs = discovered.shared;
+
+    if result.vcs.contains(&VcsType::Git) {
+        result.worktree_of = worktree::main_worktree(path);
+    }
+    result.language = language::detect_primary_language(path, &result.artifact_dirs);
+
+    result
+}
 
-    found
+/// Returns true if the given path looks like a project root.
+pub fn is_project(path: &Path, custom: &[CustomBuildSystem]) -> bool {
+    if VcsType::ALL.iter().any(|vcs| vcs.detected_in(path)) {
+        return true;
+    }
+    let dir = MarkerDir::new(path);
+    BUILD_SYSTEMS.iter().any(|info| info.detected_in(&dir))
+        || custom.iter().any(|info| info.detected_in(&dir))
 }
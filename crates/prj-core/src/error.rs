@@ -1,4 +1,5 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// Errors produced by core `prj` operations.
 #[derive(Debug, thiserror::Error)]
@@ -9,18 +10,84 @@ pub enum PrjError {
     #[error("project already registered: {0}")]
     ProjectAlreadyExists(String),
 
+    #[error("project name already in use: {0} (choose another with --name)")]
+    ProjectNameTaken(String),
+
+    #[error(
+        "'{query}' matches more than one project:\n  {}",
+        .candidates.join("\n  ")
+    )]
+    AmbiguousProject {
+        query: String,
+        candidates: Vec<String>,
+    },
+
     #[error("path does not exist: {}", .0.display())]
     PathNotFound(PathBuf),
 
     #[error("path is not a directory: {}", .0.display())]
     NotADirectory(PathBuf),
 
+    #[error("destination already exists: {}", .0.display())]
+    DestinationExists(PathBuf),
+
+    #[error(
+        "cannot move {} to {}: they are on different filesystems; move the directory yourself, then run `prj mv` without --move",
+        .from.display(),
+        .to.display()
+    )]
+    CrossDeviceMove { from: PathBuf, to: PathBuf },
+
     #[error("failed to read database: {0}")]
     DatabaseRead(#[source] Box<dyn std::error::Error + Send + Sync>),
 
     #[error("failed to write database: {0}")]
     DatabaseWrite(#[source] Box<dyn std::error::Error + Send + Sync>),
 
+    #[error(
+        "database is locked by another prj process (waited {}s): {}",
+        .timeout.as_secs(),
+        .path.display()
+    )]
+    DatabaseLocked { path: PathBuf, timeout: Duration },
+
+    #[error(
+        "database {} uses schema v{found}, but this prj only supports up to v{supported}; upgrade prj",
+        .path.display()
+    )]
+    UnsupportedSchema {
+        path: PathBuf,
+        found: u32,
+        supported: u32,
+    },
+
+    #[error("this prj was built without the {0} storage backend (rebuild with `--features {0}`)")]
+    StorageUnavailable(crate::storage::StorageBackend),
+
+    #[error("failed to migrate database from schema v{from}: {reason}")]
+    MigrationFailed { from: u32, reason: String },
+
+    #[error("invalid scan exclude pattern '{0}': {1}")]
+    InvalidScanPattern(String, String),
+
+    #[error("invalid tag: '{0}' (tags are non-empty, '/'-separated names)")]
+    InvalidTag(String),
+
+    #[error("invalid metadata key: '{0}' (keys are non-empty, without '=', ',' or whitespace)")]
+    InvalidMetaKey(String),
+
+    #[error("history journal error: {0}")]
+    Journal(String),
+
+    #[error("stats cache error: {0}")]
+    StatsCache(String),
+
+    #[error("stats history error: {0}")]
+    StatsHistory(String),
+
+    #[error("nothing to undo")]
+    NothingToUndo,
+
     #[error("git error: {0}")]
     Git(#[from] git2::Error),
 
@@ -30,10 +97,13 @@ pub enum PrjError {
     #[error("git clone failed: {0}")]
     CloneFailed(String),
 
+    #[error("git worktree add failed: {0}")]
+    WorktreeFailed(String),
+
     #[error("could not determine clone destination from args: {0}")]
     CloneDestUnknown(String),
 
-    #[error("no target projects specified (use --project, --tag, or --all)")]
+    #[error("no target projects specified (use --project, --tag, --lang, or --all)")]
     NoTargetProjects,
 
     #[error("manifest error: {0}")]
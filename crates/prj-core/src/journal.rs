@@ -0,0 +1,339 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::PrjError;
+use crate::persist;
+use crate::project::{Project, ProjectDatabase, disambiguate_name};
+
+/// Kinds of database mutation recorded in the journal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Add,
+    Scan,
+    New,
+    Import,
+    Remove,
+    Rename,
+    Move,
+    Refresh,
+    Tag,
+    Untag,
+    Tags,
+    Meta,
+    Gc,
+    Worktree,
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Operation::Add => "add",
+            Operation::Scan => "scan",
+            Operation::New => "new",
+            Operation::Import => "import",
+            Operation::Remove => "remove",
+            Operation::Rename => "rename",
+            Operation::Move => "mv",
+            Operation::Refresh => "refresh",
+            Operation::Tag => "tag",
+            Operation::Untag => "untag",
+            Operation::Tags => "tags",
+            Operation::Meta => "meta",
+            Operation::Gc => "gc",
+            Operation::Worktree => "worktree",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// One recorded mutation, with enough state to revert it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub at: DateTime<Utc>,
+    pub operation: Operation,
+    pub summary: String,
+    /// Paths of every project the operation added, removed or changed.
+    pub touched: Vec<PathBuf>,
+    /// The touched projects as they were before the operation. Projects the
+    /// operation added have no entry here.
+    #[serde(default)]
+    pub before: Vec<Project>,
+    /// Index of each `before` project in the database before the
+    /// operation, in the same order.
+    pub positions: Vec<usize>,
+    /// The directory `prj mv --move` moved, which undo moves back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moved_dir: Option<MovedDir>,
+}
+
+/// A project directory moved on disk along with its database entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MovedDir {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl MovedDir {
+    /// Move the directory back from `to` to `from`. Fails without touching
+    /// anything if `to` is gone or something now occupies `from`.
+    pub fn move_back(&self) -> Result<(), PrjError> {
+        if !self.to.exists() {
+            return Err(PrjError::PathNotFound(self.to.clone()));
+        }
+        if self.from.exists() {
+            return Err(PrjError::DestinationExists(self.from.clone()));
+        }
+        std::fs::rename(&self.to, &self.from).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::CrossesDevices {
+                PrjError::CrossDeviceMove {
+                    from: self.to.clone(),
+                    to: self.from.clone(),
+                }
+            } else {
+                PrjError::Io(e)
+            }
+        })
+    }
+}
+
+impl JournalEntry {
+    /// Describe the change from `old` to `new`, or `None` if nothing changed.
+    pub fn diff(
+        operation: Operation,
+        summary: String,
+        old: &[Project],
+        new: &[Project],
+    ) -> Option<Self> {
+        let mut touched = Vec::new();
+        let mut before = Vec::new();
+        let mut positions = Vec::new();
+        for (i, p) in old.iter().enumerate() {
+            if new.iter().find(|n| n.path == p.path) != Some(p) {
+                touched.push(p.path.clone());
+                before.push(p.clone());
+                positions.push(i);
+            }
+        }
+        for p in new {
+            if !old.iter().any(|o| o.path == p.path) {
+                touched.push(p.path.clone());
+            }
+        }
+        if touched.is_empty() {
+            return None;
+        }
+        Some(Self {
+            at: Utc::now(),
+            operation,
+            summary,
+            touched,
+            before,
+            positions,
+            moved_dir: None,
+        })
+    }
+
+    /// Revert this entry's changes in `db`.
+    ///
+    /// Touched projects are put back in place where they still exist,
+    /// projects the operation added are dropped, and removed ones re-added
+    /// at their old position. Visits are not journaled, so visit counts and
+    /// times recorded since are kept.
+    ///
+    /// A restored project whose name has since been given to another
+    /// project is renamed as registering would (`work/api`). Returns the
+    /// old and new names of those.
+    pub fn revert(&self, db: &mut ProjectDatabase) -> Vec<(String, String)> {
+        db.projects.retain(|p| {
+            !self.touched.contains(&p.path) || self.before.iter().any(|b| b.path == p.path)
+        });
+        for p in db.projects.iter_mut() {
+            if let Some(old) = self.before.iter().find(|b| b.path == p.path) {
+                *p = Project {
+                    visit_count: p.visit_count,
+                    last_visited: p.last_visited,
+                    ..old.clone()
+                };
+            }
+        }
+
+        let mut removed: Vec<(usize, &Project)> = self
+            .before
+            .iter()
+            .zip(&self.positions)
+            .filter(|(old, _)| !db.projects.iter().any(|p| p.path == old.path))
+            .map(|(old, &position)| (position, old))
+            .collect();
+        // Inserting in ascending order puts each at its old index
+        removed.sort_by_key(|(position, _)| *position);
+        for (position, old) in removed {
+            let position = position.min(db.projects.len());
+            db.projects.insert(position, old.clone());
+        }
+
+        let mut renamed = Vec::new();
+        for old in &self.before {
+            let Some(idx) = db.projects.iter().position(|p| p.path == old.path) else {
+                continue;
+            };
+            let name = db.projects[idx].name.clone();
+            let new_name = disambiguate_name(&name, &old.path, |n| {
+                db.projects
+                    .iter()
+                    .any(|p| p.name == n && p.path != old.path)
+            });
+            if new_name != name {
+                db.projects[idx].name = new_name.clone();
+                renamed.push((name, new_name));
+            }
+        }
+        renamed
+    }
+}
+
+/// Bounded history of database mutations, oldest first.
+///
+/// Stored next to the database as `<database file>.history.toml` (e.g.
+/// `projects.db.history.toml`), so each storage backend has its own, and
+/// only modified while the database lock is held.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Journal {
+    #[serde(default)]
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Journal location for a database stored at `db_path`.
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        persist::sibling_path(db_path, "history.toml")
+    }
+
+    /// Load the journal, or return an empty one if it doesn't exist.
+    pub fn load(path: &Path) -> Result<Self, PrjError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content =
+            std::fs::read_to_string(path).map_err(|e| PrjError::Journal(e.to_string()))?;
+        toml::from_str(&content).map_err(|e| PrjError::Journal(e.to_string()))
+    }
+
+    /// Atomically write the journal.
+    pub fn save(&self, path: &Path) -> Result<(), PrjError> {
+        let content = toml::to_string_pretty(self).map_err(|e| PrjError::Journal(e.to_string()))?;
+        persist::write_atomic(path, content.as_bytes())
+            .map_err(|e| PrjError::Journal(e.to_string()))
+    }
+
+    /// Append an entry, dropping the oldest ones beyond `limit`.
+    pub fn push(&mut self, entry: JournalEntry, limit: usize) {
+        self.entries.push(entry);
+        let excess = self.entries.len().saturating_sub(limit);
+        self.entries.drain(..excess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(name: &str) -> Project {
+        Project {
+            name: name.to_string(),
+            path: PathBuf::from(format!("/projects/{name}")),
+            vcs: Vec::new(),
+            build_systems: Vec::new(),
+            artifact_dirs: Vec::new(),
+            shared_artifact_dirs: Vec::new(),
+            added_at: DateTime::UNIX_EPOCH,
+            tags: Vec::new(),
+            description: None,
+            metadata: Default::default(),
+            visit_count: 0,
+            last_visited: None,
+            detected_at: None,
+            parent: None,
+            toolchains: Default::default(),
+            worktree_of: None,
+            language: None,
+        }
+    }
+
+    fn database(projects: Vec<Project>) -> ProjectDatabase {
+        ProjectDatabase {
+            version: crate::migrate::CURRENT_VERSION,
+            projects,
+        }
+    }
+
+    fn names(db: &ProjectDatabase) -> Vec<&str> {
+        db.projects.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn revert_restores_removed_projects_at_their_position() {
+        let old = vec![project("a"), project("b"), project("c"), project("d")];
+        let new = vec![project("a"), project("c")];
+        let entry = JournalEntry::diff(Operation::Gc, String::new(), &old, &new).unwrap();
+
+        let mut db = database(new);
+        entry.revert(&mut db);
+        assert_eq!(names(&db), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn revert_drops_added_projects() {
+        let old = vec![project("a")];
+        let new = vec![project("a"), project("b")];
+        let entry = JournalEntry::diff(Operation::Add, String::new(), &old, &new).unwrap();
+
+        let mut db = database(new);
+        entry.revert(&mut db);
+        assert_eq!(names(&db), ["a"]);
+    }
+
+    #[test]
+    fn revert_keeps_newer_visits() {
+        let old = vec![project("a")];
+        let mut tagged = project("a");
+        tagged.tags = vec!["work".to_string()];
+        let entry =
+            JournalEntry::diff(Operation::Tag, String::new(), &old, &[tagged.clone()]).unwrap();
+
+        tagged.visit_count = 3;
+        tagged.last_visited = Some(Utc::now());
+        let mut db = database(vec![tagged.clone()]);
+        entry.revert(&mut db);
+        assert!(db.projects[0].tags.is_empty());
+        assert_eq!(db.projects[0].visit_count, 3);
+        assert_eq!(db.projects[0].last_visited, tagged.last_visited);
+    }
+
+    #[test]
+    fn revert_renames_projects_whose_name_was_taken() {
+        let old = vec![project("foo")];
+        let entry = JournalEntry::diff(Operation::Remove, String::new(), &old, &[]).unwrap();
+
+        let mut other = project("foo");
+        other.path = PathBuf::from("/elsewhere/foo");
+        let mut db = database(vec![other]);
+        let renamed = entry.revert(&mut db);
+        assert_eq!(names(&db), ["projects/foo", "foo"]);
+        assert_eq!(renamed, [("foo".to_string(), "projects/foo".to_string())]);
+    }
+
+    #[test]
+    fn journal_is_named_after_the_database() {
+        assert_eq!(
+            Journal::path_for(Path::new("/data/prj/projects.toml")),
+            Path::new("/data/prj/projects.toml.history.toml")
+        );
+        assert_eq!(
+            Journal::path_for(Path::new("/data/prj/projects.db")),
+            Path::new("/data/prj/projects.db.history.toml")
+        );
+    }
+}
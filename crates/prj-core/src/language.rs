@@ -0,0 +1,76 @@
+//! Cheap primary-language classification.
+//!
+//! Instead of a full tokei pass, up to [`SAMPLE_LIMIT`] files are
+//! classified by extension and weighted by size. Data, documentation and
+//! build files only count when a project has no source code at all, so a
+//! `Makefile` project is reported as C, Go or TeX rather than Makefile.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use ignore::WalkBuilder;
+use tokei::LanguageType;
+
+/// Maximum number of files looked at per project.
+pub const SAMPLE_LIMIT: usize = 2000;
+
+/// Languages that describe data, docs or the build rather than the code.
+const NON_CODE: &[LanguageType] = &[
+    LanguageType::Json,
+    LanguageType::Yaml,
+    LanguageType::Toml,
+    LanguageType::Markdown,
+    LanguageType::Text,
+    LanguageType::Xml,
+    LanguageType::Svg,
+    LanguageType::Ini,
+    LanguageType::ReStructuredText,
+    LanguageType::Makefile,
+    LanguageType::CMake,
+    LanguageType::Autoconf,
+    LanguageType::Dockerfile,
+];
+
+/// The project's primary language by sampled file size, using tokei's
+/// language names (`Rust`, `C++`, `TeX`). Honours ignore files and skips
+/// hidden and `artifact_dirs` directories. Returns `None` when no file
+/// has a recognised extension.
+pub fn detect_primary_language(path: &Path, artifact_dirs: &[String]) -> Option<String> {
+    let artifacts: Vec<std::path::PathBuf> = artifact_dirs.iter().map(|d| path.join(d)).collect();
+    let walker = WalkBuilder::new(path)
+        .require_git(false)
+        .filter_entry(move |e| !artifacts.iter().any(|a| e.path() == a))
+        .build();
+
+    let mut bytes: HashMap<LanguageType, u64> = HashMap::new();
+    let mut sampled = 0;
+    for entry in walker.filter_map(|e| e.ok()) {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        sampled += 1;
+        if sampled > SAMPLE_LIMIT {
+            break;
+        }
+        let Some(lang) = entry
+            .path()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| LanguageType::from_file_extension(&ext.to_lowercase()))
+        else {
+            continue;
+        };
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        // Count empty files as one byte so they still register
+        *bytes.entry(lang).or_default() += size.max(1);
+    }
+
+    let largest = |code_only: bool| {
+        bytes
+            .iter()
+            .filter(|(lang, _)| !code_only || !NON_CODE.contains(lang))
+            .max_by_key(|(lang, size)| (**size, std::cmp::Reverse(lang.name())))
+            .map(|(lang, _)| lang.name().to_string())
+    };
+    largest(true).or_else(|| largest(false))
+}
@@ -1,12 +1,28 @@
 //! Core library for `prj` — a local project manager.
 //!
-//! Provides project detection, database persistence, statistics collection,
-//! artifact cleaning, and workspace export/import.
+//! Provides project detection, primary-language classification, database
+//! persistence, statistics collection and history, stale project
+//! detection, artifact cleaning, monorepo workspace members, git
+//! worktrees, toolchain requirements, and workspace export/import.
 
+pub mod artifacts;
 pub mod clean;
 pub mod config;
 pub mod detect;
 pub mod error;
+pub mod journal;
+pub mod language;
 pub mod manifest;
+pub mod migrate;
+pub mod persist;
 pub mod project;
+pub mod scan;
+pub mod stale;
 pub mod stats;
+pub mod stats_cache;
+pub mod stats_history;
+pub mod storage;
+pub mod tags;
+pub mod toolchain;
+pub mod workspace;
+pub mod worktree;
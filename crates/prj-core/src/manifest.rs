@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
@@ -16,12 +17,16 @@ pub struct Manifest {
 }
 
 /// A single project entry within a [`Manifest`].
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ManifestEntry {
     pub name: String,
     pub relative_path: String,
     pub remote_url: Option<String>,
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
 }
 
 /// Compute the longest common prefix of all project paths.
@@ -81,6 +86,8 @@ pub fn export(projects: &[Project], base_dir: Option<&Path>) -> Manifest {
                 relative_path,
                 remote_url,
                 tags: p.tags.clone(),
+                description: p.description.clone(),
+                metadata: p.metadata.clone(),
             }
         })
         .collect();
@@ -116,15 +123,7 @@ pub fn import_targets(
         .iter()
         .map(|entry| {
             let target = base.join(&entry.relative_path);
-            (
-                ManifestEntry {
-                    name: entry.name.clone(),
-                    relative_path: entry.relative_path.clone(),
-                    remote_url: entry.remote_url.clone(),
-                    tags: entry.tags.clone(),
-                },
-                target,
-            )
+            (entry.clone(), target)
         })
         .collect()
 }
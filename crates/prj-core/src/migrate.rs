@@ -0,0 +1,285 @@
+use std::path::Path;
+
+use toml::{Table, Value};
+
+use crate::error::PrjError;
+use crate::persist;
+use crate::project::disambiguate_name;
+
+/// Schema version of `projects.toml` written by this build.
+///
+/// Bump this and append a step to [`MIGRATIONS`] whenever the on-disk layout
+/// changes, including purely additive fields: an older `prj` would otherwise
+/// load the file, silently drop the fields it doesn't know, and save it back.
+pub const CURRENT_VERSION: u32 = 11;
+
+/// A single upgrade step from version `N` to `N + 1`.
+type Migration = fn(&mut Table) -> Result<(), String>;
+
+/// Upgrade steps, indexed by the version they upgrade *from*.
+const MIGRATIONS: &[Migration] = &[
+    v0_to_v1, v1_to_v2, v2_to_v3, v3_to_v4, v4_to_v5, v5_to_v6, v6_to_v7, v7_to_v8, v8_to_v9,
+    v9_to_v10, v10_to_v11,
+];
+
+/// Files written before schema versioning have no `version` key but are
+/// otherwise identical to v1.
+fn v0_to_v1(_db: &mut Table) -> Result<(), String> {
+    Ok(())
+}
+
+/// v2 adds per-project `visit_count` and `last_visited`, which default to
+/// "never visited" when absent.
+fn v1_to_v2(_db: &mut Table) -> Result<(), String> {
+    Ok(())
+}
+
+/// v3 requires unique project names. Duplicates left by older versions keep
+/// their name on the first occurrence and are qualified with parent
+/// directories on the rest.
+fn v2_to_v3(db: &mut Table) -> Result<(), String> {
+    let Some(Value::Array(projects)) = db.get_mut("projects") else {
+        return Ok(());
+    };
+    let mut names: Vec<String> = Vec::new();
+    for project in projects.iter_mut() {
+        let Some(table) = project.as_table_mut() else {
+            return Err("project entry is not a table".to_string());
+        };
+        let name = table
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or("project entry has no name")?
+            .to_string();
+        let path = table
+            .get("path")
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("project {name} has no path"))?;
+        let unique = disambiguate_name(&name, Path::new(path), |n| names.iter().any(|t| t == n));
+        if unique != name {
+            table.insert("name".to_string(), Value::String(unique.clone()));
+        }
+        names.push(unique);
+    }
+    Ok(())
+}
+
+/// v4 adds per-project `detected_at`; absent means "unknown".
+fn v3_to_v4(_db: &mut Table) -> Result<(), String> {
+    Ok(())
+}
+
+/// v5 adds per-project `description` and a `metadata` table, both empty
+/// when absent.
+fn v4_to_v5(_db: &mut Table) -> Result<(), String> {
+    Ok(())
+}
+
+/// v6 allows `build_systems` entries other than the built-in names, for
+/// build systems declared in the config. Existing entries are unchanged.
+fn v5_to_v6(_db: &mut Table) -> Result<(), String> {
+    Ok(())
+}
+
+/// v7 adds per-project `parent`, linking workspace members to their
+/// workspace root; absent means a top-level project.
+fn v6_to_v7(_db: &mut Table) -> Result<(), String> {
+    Ok(())
+}
+
+/// v8 adds per-project `shared_artifact_dirs`, empty when absent until
+/// the next `prj refresh`.
+fn v7_to_v8(_db: &mut Table) -> Result<(), String> {
+    Ok(())
+}
+
+/// v9 adds per-project `toolchains`, empty when absent until the next
+/// `prj refresh`.
+fn v8_to_v9(_db: &mut Table) -> Result<(), String> {
+    Ok(())
+}
+
+/// v10 adds per-project `worktree_of`, linking git worktrees to their
+/// main working tree; set on the next `prj refresh`.
+fn v9_to_v10(_db: &mut Table) -> Result<(), String> {
+    Ok(())
+}
+
+/// v11 adds per-project `language`, unset until the next `prj refresh`.
+fn v10_to_v11(_db: &mut Table) -> Result<(), String> {
+    Ok(())
+}
+
+/// Read the schema version of a raw database table (absent means v0).
+pub fn schema_version(db: &Table) -> Result<u32, PrjError> {
+    match db.get("version") {
+        None => Ok(0),
+        Some(Value::Integer(v)) => u32::try_from(*v).map_err(|_| PrjError::MigrationFailed {
+            from: 0,
+            reason: format!("invalid schema version: {v}"),
+        }),
+        Some(other) => Err(PrjError::MigrationFailed {
+            from: 0,
+            reason: format!("invalid schema version: {other}"),
+        }),
+    }
+}
+
+/// Returns true if the table is older than [`CURRENT_VERSION`].
+///
+/// Fails with [`PrjError::UnsupportedSchema`] if the file was written by a
+/// newer `prj` that this build cannot safely read.
+pub fn needs_migration(db: &Table, path: &Path) -> Result<bool, PrjError> {
+    let version = schema_version(db)?;
+    if version > CURRENT_VERSION {
+        return Err(PrjError::UnsupportedSchema {
+            path: path.to_path_buf(),
+            found: version,
+            supported: CURRENT_VERSION,
+        });
+    }
+    Ok(version < CURRENT_VERSION)
+}
+
+/// Run every migration step from the table's version up to [`CURRENT_VERSION`].
+pub fn migrate(db: &mut Table) -> Result<(), PrjError> {
+    let from = schema_version(db)?;
+    for (version, step) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        step(db).map_err(|reason| PrjError::MigrationFailed {
+            from: version as u32,
+            reason,
+        })?;
+    }
+    db.insert(
+        "version".to_string(),
+        Value::Integer(CURRENT_VERSION.into()),
+    );
+    Ok(())
+}
+
+/// Copy the database to `<file>.v<version>.bak` before it is upgraded.
+pub fn write_backup(path: &Path, version: u32) -> Result<(), PrjError> {
+    let backup = persist::sibling_path(path, &format!("v{version}.bak"));
+    std::fs::copy(path, &backup).map_err(|e| PrjError::MigrationFailed {
+        from: version,
+        reason: format!("could not write backup {}: {e}", backup.display()),
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::ProjectDatabase;
+
+    fn parse(toml: &str) -> Table {
+        toml.parse().expect("valid TOML")
+    }
+
+    #[test]
+    fn upgrades_unversioned_database_to_current() {
+        let mut raw = parse(
+            r#"
+            [[projects]]
+            name = "app"
+            path = "/home/me/app"
+            vcs = ["Git"]
+            build_systems = ["Cargo"]
+            artifact_dirs = ["target"]
+            added_at = "2024-01-01T00:00:00Z"
+            tags = ["work"]
+            "#,
+        );
+        assert_eq!(schema_version(&raw).unwrap(), 0);
+        assert!(needs_migration(&raw, Path::new("projects.toml")).unwrap());
+
+        migrate(&mut raw).unwrap();
+
+        assert_eq!(schema_version(&raw).unwrap(), CURRENT_VERSION);
+        assert!(!needs_migration(&raw, Path::new("projects.toml")).unwrap());
+        let db: ProjectDatabase = raw.try_into().unwrap();
+        assert_eq!(db.version, CURRENT_VERSION);
+        let project = &db.projects[0];
+        assert_eq!(project.name, "app");
+        assert_eq!(project.tags, ["work"]);
+    }
+
+    #[test]
+    fn v2_to_v3_qualifies_duplicate_names() {
+        let mut raw = parse(
+            r#"
+            version = 2
+
+            [[projects]]
+            name = "api"
+            path = "/home/me/work/api"
+
+            [[projects]]
+            name = "api"
+            path = "/home/me/oss/api"
+
+            [[projects]]
+            name = "api"
+            path = "/srv/oss/api"
+            "#,
+        );
+        migrate(&mut raw).unwrap();
+
+        let names: Vec<&str> = raw["projects"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["api", "oss/api", "srv/oss/api"]);
+    }
+
+    #[test]
+    fn v2_to_v3_rejects_project_without_name() {
+        let mut raw = parse(
+            r#"
+            version = 2
+
+            [[projects]]
+            path = "/home/me/api"
+            "#,
+        );
+        let err = migrate(&mut raw).unwrap_err();
+        assert!(matches!(err, PrjError::MigrationFailed { from: 2, .. }));
+    }
+
+    #[test]
+    fn refuses_database_from_newer_version() {
+        let raw = parse(&format!("version = {}", CURRENT_VERSION + 1));
+        let err = needs_migration(&raw, Path::new("projects.toml")).unwrap_err();
+        match err {
+            PrjError::UnsupportedSchema {
+                found, supported, ..
+            } => {
+                assert_eq!(found, CURRENT_VERSION + 1);
+                assert_eq!(supported, CURRENT_VERSION);
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_version() {
+        let raw = parse(r#"version = "two""#);
+        assert!(schema_version(&raw).is_err());
+        let raw = parse("version = -1");
+        assert!(schema_version(&raw).is_err());
+    }
+
+    #[test]
+    fn backup_keeps_original_contents() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("projects.toml");
+        std::fs::write(&path, "version = 1\n").unwrap();
+
+        write_backup(&path, 1).unwrap();
+
+        let backup = dir.path().join("projects.toml.v1.bak");
+        assert_eq!(std::fs::read_to_string(backup).unwrap(), "version = 1\n");
+    }
+}
@@ -0,0 +1,85 @@
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::error::PrjError;
+
+/// How often a blocked [`FileLock::acquire`] retries.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// An exclusive advisory lock on a file, released when dropped.
+///
+/// The lock lives on a dedicated `<file>.lock` sibling so the locked file
+/// itself can be atomically replaced while the lock is held.
+#[derive(Debug)]
+pub struct FileLock {
+    file: File,
+}
+
+impl FileLock {
+    /// Acquire an exclusive lock guarding `path`, waiting up to `timeout`.
+    pub fn acquire(path: &Path, timeout: Duration) -> Result<Self, PrjError> {
+        let lock_path = sibling_path(path, "lock");
+        if let Some(parent) = lock_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Self { file }),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    std::thread::sleep(LOCK_POLL_INTERVAL);
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(PrjError::DatabaseLocked {
+                        path: path.to_path_buf(),
+                        timeout,
+                    });
+                }
+                Err(TryLockError::Error(e)) => return Err(PrjError::Io(e)),
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+/// Write `contents` to `path` so readers only ever see the old or the new file.
+///
+/// The data is written and synced to a temporary sibling first, then renamed
+/// over the destination.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp_path = sibling_path(path, "tmp");
+    let result = (|| {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// `projects.toml` + `lock` -> `projects.toml.lock`.
+pub(crate) fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
@@ -1,108 +1,637 @@
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::config::Config;
-use crate::detect::{BuildSystem, VcsType};
+use crate::detect::{BuildSystem, CustomBuildSystem, VcsType};
 use crate::error::PrjError;
+use crate::journal::{Journal, JournalEntry, MovedDir, Operation};
+use crate::migrate;
+use crate::persist::FileLock;
+use crate::storage::{self, Storage};
+use crate::tags;
+use crate::toolchain::{Toolchains, toolchain_list};
+use crate::workspace;
 
 /// A registered project with its detected metadata.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Project {
     pub name: String,
     pub path: PathBuf,
     pub vcs: Vec<VcsType>,
     pub build_systems: Vec<BuildSystem>,
     pub artifact_dirs: Vec<String>,
+    /// Configured build directories outside the project, such as a shared
+    /// `CARGO_TARGET_DIR`. Shown but never cleaned.
+    #[serde(default)]
+    pub shared_artifact_dirs: Vec<String>,
     pub added_at: DateTime<Utc>,
     #[serde(default)]
     pub tags: Vec<String>,
+    /// Free-form notes about the project.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Arbitrary user-defined fields such as `owner` or `deploy-env`.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// Number of times the project was selected in the picker.
+    #[serde(default)]
+    pub visit_count: u32,
+    /// When the project was last selected in the picker.
+    #[serde(default)]
+    pub last_visited: Option<DateTime<Utc>>,
+    /// When `vcs`, `build_systems` and `artifact_dirs` were last detected.
+    #[serde(default)]
+    pub detected_at: Option<DateTime<Utc>>,
+    /// Path of the workspace root this project is a member of, if any.
+    #[serde(default)]
+    pub parent: Option<PathBuf>,
+    /// Toolchain versions pinned by the project, keyed by tool
+    /// (`rust`, `node`, `python`, ...).
+    #[serde(default)]
+    pub toolchains: Toolchains,
+    /// Main working tree this project is a linked git worktree of.
+    #[serde(default)]
+    pub worktree_of: Option<PathBuf>,
+    /// Primary language, e.g. `Rust` or `C++`, sampled at detection time.
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+/// Metadata key that reads and writes [`Project::description`].
+pub const DESCRIPTION_KEY: &str = "description";
+
+/// Added and removed values of one detected field.
+#[derive(Debug)]
+pub struct FieldDiff {
+    pub field: &'static str,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// What changed when a project's detection was re-run.
+#[derive(Debug, Default)]
+pub struct RefreshDiff {
+    pub changes: Vec<FieldDiff>,
+}
+
+impl RefreshDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn compare<T: PartialEq + ToString>(&mut self, field: &'static str, old: &[T], new: &[T]) {
+        let added: Vec<String> = new
+            .iter()
+            .filter(|v| !old.contains(v))
+            .map(|v| v.to_string())
+            .collect();
+        let removed: Vec<String> = old
+            .iter()
+            .filter(|v| !new.contains(v))
+            .map(|v| v.to_string())
+            .collect();
+        if !added.is_empty() || !removed.is_empty() {
+            self.changes.push(FieldDiff {
+                field,
+                added,
+                removed,
+            });
+        }
+    }
+}
+
+impl Project {
+    /// Zoxide-style frecency: the visit count weighted by how recently the
+    /// project was last visited. Never-visited projects score zero.
+    pub fn frecency(&self, now: DateTime<Utc>) -> f64 {
+        let Some(last_visited) = self.last_visited else {
+            return 0.0;
+        };
+        let age = now - last_visited;
+        let weight = if age < TimeDelta::hours(1) {
+            4.0
+        } else if age < TimeDelta::days(1) {
+            2.0
+        } else if age < TimeDelta::weeks(1) {
+            0.5
+        } else {
+            0.25
+        };
+        f64::from(self.visit_count) * weight
+    }
+
+    /// Returns true if the project has `filter` or a tag nested below it.
+    pub fn has_tag(&self, filter: &str) -> bool {
+        self.tags.iter().any(|t| tags::matches(t, filter))
+    }
+
+    /// Returns true if the primary language is `lang`, ignoring case.
+    pub fn has_language(&self, lang: &str) -> bool {
+        self.language
+            .as_deref()
+            .is_some_and(|l| l.eq_ignore_ascii_case(lang))
+    }
+
+    /// Get a metadata value; `description` reads the description.
+    pub fn get_meta(&self, key: &str) -> Option<&str> {
+        if key == DESCRIPTION_KEY {
+            self.description.as_deref()
+        } else {
+            self.metadata.get(key).map(String::as_str)
+        }
+    }
+
+    /// Set a metadata value; `description` sets the description. Keys
+    /// must be non-empty and free of `=`, `,` and whitespace, which
+    /// separate fields in `prj list --plain`.
+    pub fn set_meta(&mut self, key: &str, value: &str) -> Result<(), PrjError> {
+        if key.is_empty() || key.contains(['=', ',']) || key.contains(char::is_whitespace) {
+            return Err(PrjError::InvalidMetaKey(key.to_string()));
+        }
+        if key == DESCRIPTION_KEY {
+            self.description = Some(value.to_string());
+        } else {
+            self.metadata.insert(key.to_string(), value.to_string());
+        }
+        Ok(())
+    }
+
+    /// Remove a metadata value, returning whether it was set.
+    pub fn unset_meta(&mut self, key: &str) -> bool {
+        if key == DESCRIPTION_KEY {
+            self.description.take().is_some()
+        } else {
+            self.metadata.remove(key).is_some()
+        }
+    }
+
+    /// Re-run detection on the project directory, keeping user data
+    /// (name, tags, `added_at`, visits), and report what changed.
+    pub fn refresh(&mut self, custom: &[CustomBuildSystem]) -> RefreshDiff {
+        let detection = crate::detect::detect_project(&self.path, custom);
+        let mut diff = RefreshDiff::default();
+        diff.compare("vcs", &self.vcs, &detection.vcs);
+        diff.compare("build", &self.build_systems, &detection.build_systems);
+        diff.compare("artifacts", &self.artifact_dirs, &detection.artifact_dirs);
+        diff.compare(
+            "shared artifacts",
+            &self.shared_artifact_dirs,
+            &detection.shared_artifact_dirs,
+        );
+        diff.compare(
+            "toolchains",
+            &toolchain_list(&self.toolchains),
+            &toolchain_list(&detection.toolchains),
+        );
+        let display = |p: &Option<PathBuf>| -> Vec<String> {
+            p.iter().map(|p| p.display().to_string()).collect()
+        };
+        diff.compare(
+            "worktree of",
+            &display(&self.worktree_of),
+            &display(&detection.worktree_of),
+        );
+        diff.compare(
+            "language",
+            self.language.as_slice(),
+            detection.language.as_slice(),
+        );
+
+        self.vcs = detection.vcs;
+        self.build_systems = detection.build_systems;
+        self.artifact_dirs = detection.artifact_dirs;
+        self.shared_artifact_dirs = detection.shared_artifact_dirs;
+        self.toolchains = detection.toolchains;
+        self.worktree_of = detection.worktree_of;
+        self.language = detection.language;
+        self.detected_at = Some(Utc::now());
+        diff
+    }
 }
 
 /// Persistent store of all registered projects, serialized as TOML.
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ProjectDatabase {
+    /// Schema version, see [`migrate::CURRENT_VERSION`].
+    #[serde(default)]
+    pub version: u32,
     #[serde(default)]
     pub projects: Vec<Project>,
 }
 
+impl Default for ProjectDatabase {
+    fn default() -> Self {
+        Self {
+            version: migrate::CURRENT_VERSION,
+            projects: Vec::new(),
+        }
+    }
+}
+
 impl ProjectDatabase {
-    /// Load the database from disk, or return an empty one if it doesn't exist.
+    /// Load the database from storage, or return an empty one if nothing is stored yet.
+    ///
+    /// This is a read-only snapshot. Use [`ProjectDatabase::lock`] when the
+    /// database will be modified and saved. Outdated data is upgraded in
+    /// place first, which briefly takes the lock.
     pub fn load(config: &Config) -> Result<Self, PrjError> {
-        let path = config.database_path();
-        if path.exists() {
-            let content =
-                std::fs::read_to_string(&path).map_err(|e| PrjError::DatabaseRead(Box::new(e)))?;
-            toml::from_str(&content).map_err(|e| PrjError::DatabaseRead(Box::new(e)))
-        } else {
-            Ok(Self::default())
+        let storage = storage::open(config)?;
+        let Some(raw) = storage.read_raw()? else {
+            return Ok(Self::default());
+        };
+        if migrate::needs_migration(&raw, storage.path())? {
+            let LockedDatabase { db, .. } = Self::lock(config)?;
+            return Ok(db);
         }
+        Self::from_raw(raw)
+    }
+
+    /// Load the database while holding an exclusive lock on it.
+    ///
+    /// The lock is held until the returned [`LockedDatabase`] is dropped, so
+    /// other `prj` processes cannot interleave their own load/save cycle.
+    /// Outdated data is backed up, migrated and written back.
+    pub fn lock(config: &Config) -> Result<LockedDatabase, PrjError> {
+        Self::lock_storage(storage::open(config)?, config)
     }
 
-    /// Save the database to disk.
+    /// Like [`ProjectDatabase::lock`], but for an explicit storage backend.
+    pub fn lock_storage(
+        storage: Box<dyn Storage>,
+        config: &Config,
+    ) -> Result<LockedDatabase, PrjError> {
+        let lock = FileLock::acquire(storage.path(), config.lock_timeout())?;
+        let db = match storage.read_raw()? {
+            None => Self::default(),
+            Some(mut raw) => {
+                if migrate::needs_migration(&raw, storage.path())? {
+                    storage.backup(migrate::schema_version(&raw)?)?;
+                    migrate::migrate(&mut raw)?;
+                    let db = Self::from_raw(raw)?;
+                    storage.write(&db)?;
+                    db
+                } else {
+                    Self::from_raw(raw)?
+                }
+            }
+        };
+        Ok(LockedDatabase {
+            original: db.projects.clone(),
+            stored: db.projects.clone(),
+            db,
+            storage,
+            history_limit: config.history_limit,
+            _lock: lock,
+        })
+    }
+
+    /// Save the database, briefly taking the database lock.
+    ///
+    /// Must not be called while this process holds a [`LockedDatabase`];
+    /// use [`LockedDatabase::save`] instead.
     pub fn save(&self, config: &Config) -> Result<(), PrjError> {
-        let path = config.database_path();
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent).map_err(|e| PrjError::DatabaseWrite(Box::new(e)))?;
-        }
-        let content =
-            toml::to_string_pretty(self).map_err(|e| PrjError::DatabaseWrite(Box::new(e)))?;
-        std::fs::write(&path, content).map_err(|e| PrjError::DatabaseWrite(Box::new(e)))?;
-        Ok(())
+        let storage = storage::open(config)?;
+        let _lock = FileLock::acquire(storage.path(), config.lock_timeout())?;
+        storage.write(self)
     }
 
-    /// Add a project. Returns error if a project with the same path already exists.
+    fn from_raw(raw: toml::Table) -> Result<Self, PrjError> {
+        raw.try_into()
+            .map_err(|e| PrjError::DatabaseRead(Box::new(e)))
+    }
+
+    /// Add a project. Returns error if a project with the same path or name already exists.
     pub fn add(&mut self, project: Project) -> Result<(), PrjError> {
         if self.projects.iter().any(|p| p.path == project.path) {
             return Err(PrjError::ProjectAlreadyExists(
                 project.path.display().to_string(),
             ));
         }
+        if self.name_taken(&project.name) {
+            return Err(PrjError::ProjectNameTaken(project.name));
+        }
         self.projects.push(project);
         Ok(())
     }
 
-    /// Remove a project by name. Returns error if not found.
-    pub fn remove(&mut self, name: &str) -> Result<Project, PrjError> {
-        let idx = self
+    /// Remove a project by name, path, or unique path suffix. Workspace
+    /// members of a removed root stay registered as top-level projects.
+    pub fn remove(&mut self, query: &str) -> Result<Project, PrjError> {
+        let idx = self.resolve_index(query)?;
+        let removed = self.projects.remove(idx);
+        for member in self
             .projects
+            .iter_mut()
+            .filter(|p| p.parent.as_ref() == Some(&removed.path))
+        {
+            member.parent = None;
+        }
+        Ok(removed)
+    }
+
+    /// Registered workspace members of `parent`.
+    pub fn members(&self, parent: &Project) -> Vec<&Project> {
+        self.projects
             .iter()
-            .position(|p| p.name == name)
-            .ok_or_else(|| PrjError::ProjectNotFound(name.to_string()))?;
-        Ok(self.projects.remove(idx))
+            .filter(|p| p.parent.as_ref() == Some(&parent.path))
+            .collect()
     }
 
-    /// Find a project by name.
+    /// Register the workspace members declared by the project matching
+    /// `query` as its children, named `parent/member`. Members that are
+    /// already registered are left alone. Returns the names added.
+    pub fn register_members(
+        &mut self,
+        query: &str,
+        custom: &[CustomBuildSystem],
+    ) -> Result<Vec<String>, PrjError> {
+        let idx = self.resolve_index(query)?;
+        let parent_name = self.projects[idx].name.clone();
+        let parent_path = self.projects[idx].path.clone();
+
+        let mut added = Vec::new();
+        for path in workspace::workspace_members(&parent_path, custom) {
+            if self.projects.iter().any(|p| p.path == path) {
+                continue;
+            }
+            let dir = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            let name = format!("{parent_name}/{dir}");
+            let project = self.register_disambiguated(&path, Some(&name), custom)?;
+            let name = project.name.clone();
+            self.projects.last_mut().expect("just pushed").parent = Some(parent_path.clone());
+            added.push(name);
+        }
+        Ok(added)
+    }
+
+    /// Find a project by exact name.
     pub fn find(&self, name: &str) -> Option<&Project> {
         self.projects.iter().find(|p| p.name == name)
     }
 
-    /// Find a project by name (mutable).
+    /// Find a project by exact name (mutable).
     pub fn find_mut(&mut self, name: &str) -> Option<&mut Project> {
         self.projects.iter_mut().find(|p| p.name == name)
     }
 
+    /// Look up a project by name, path, or unique path suffix.
+    ///
+    /// An exact name wins; otherwise `query` is tried as a path to a project
+    /// directory, then as a trailing run of path components (`oss/api`).
+    /// Fails with [`PrjError::AmbiguousProject`] listing the candidates when
+    /// more than one project matches.
+    pub fn resolve(&self, query: &str) -> Result<&Project, PrjError> {
+        self.resolve_index(query).map(|i| &self.projects[i])
+    }
+
+    /// Like [`ProjectDatabase::resolve`], but mutable.
+    pub fn resolve_mut(&mut self, query: &str) -> Result<&mut Project, PrjError> {
+        self.resolve_index(query).map(|i| &mut self.projects[i])
+    }
+
+    fn resolve_index(&self, query: &str) -> Result<usize, PrjError> {
+        let by_name: Vec<usize> = self.positions(|p| p.name == query);
+        if !by_name.is_empty() {
+            return self.single_match(query, by_name);
+        }
+
+        if let Ok(path) = Path::new(query).canonicalize()
+            && let Some(idx) = self.projects.iter().position(|p| p.path == path)
+        {
+            return Ok(idx);
+        }
+
+        let by_suffix = self.positions(|p| p.path.ends_with(query));
+        self.single_match(query, by_suffix)
+    }
+
+    fn positions(&self, pred: impl Fn(&Project) -> bool) -> Vec<usize> {
+        self.projects
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| pred(p))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn single_match(&self, query: &str, matches: Vec<usize>) -> Result<usize, PrjError> {
+        match matches.as_slice() {
+            [] => Err(PrjError::ProjectNotFound(query.to_string())),
+            [idx] => Ok(*idx),
+            _ => Err(PrjError::AmbiguousProject {
+                query: query.to_string(),
+                candidates: matches
+                    .iter()
+                    .map(|&i| {
+                        let p = &self.projects[i];
+                        format!("{} ({})", p.name, p.path.display())
+                    })
+                    .collect(),
+            }),
+        }
+    }
+
+    /// Returns true if a registered project already uses `name`.
+    pub fn name_taken(&self, name: &str) -> bool {
+        self.projects.iter().any(|p| p.name == name)
+    }
+
     /// Add tags to a project.
-    pub fn add_tags(&mut self, name: &str, tags: &[String]) -> Result<(), PrjError> {
-        let project = self
-            .find_mut(name)
-            .ok_or_else(|| PrjError::ProjectNotFound(name.to_string()))?;
-        for tag in tags {
-            if !project.tags.contains(tag) {
-                project.tags.push(tag.clone());
+    pub fn add_tags(&mut self, query: &str, new_tags: &[String]) -> Result<(), PrjError> {
+        let new_tags = new_tags
+            .iter()
+            .map(|t| tags::normalize(t))
+            .collect::<Result<Vec<_>, _>>()?;
+        let project = self.resolve_mut(query)?;
+        for tag in new_tags {
+            if !project.tags.contains(&tag) {
+                project.tags.push(tag);
             }
         }
         project.tags.sort();
         Ok(())
     }
 
-    /// Remove tags from a project.
-    pub fn remove_tags(&mut self, name: &str, tags: &[String]) -> Result<(), PrjError> {
+    /// Remove tags from a project. Only exact tags are removed; nested tags
+    /// such as `work/backend` are kept when removing `work`.
+    pub fn remove_tags(&mut self, query: &str, old_tags: &[String]) -> Result<(), PrjError> {
+        let old_tags = old_tags
+            .iter()
+            .map(|t| tags::normalize(t))
+            .collect::<Result<Vec<_>, _>>()?;
+        let project = self.resolve_mut(query)?;
+        project.tags.retain(|t| !old_tags.contains(t));
+        Ok(())
+    }
+
+    /// Projects tagged with `filter` or a tag nested below it.
+    pub fn tagged(&self, filter: &str) -> Vec<&Project> {
+        self.projects.iter().filter(|p| p.has_tag(filter)).collect()
+    }
+
+    /// Every tag in use, including implied parents (`work` for
+    /// `work/backend`), with the number of projects it matches.
+    pub fn tag_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for project in &self.projects {
+            let mut seen: Vec<&str> = project
+                .tags
+                .iter()
+                .flat_map(|t| tags::with_ancestors(t))
+                .collect();
+            seen.sort();
+            seen.dedup();
+            for tag in seen {
+                *counts.entry(tag.to_string()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Rename `old` to `new` on every project, moving nested tags along
+    /// (`work/backend` -> `job/backend`). Returns the number of projects changed.
+    pub fn rename_tag(&mut self, old: &str, new: &str) -> Result<usize, PrjError> {
+        let old = tags::normalize(old)?;
+        let new = tags::normalize(new)?;
+        let mut changed = 0;
+        for project in self.projects.iter_mut().filter(|p| p.has_tag(&old)) {
+            for tag in project.tags.iter_mut() {
+                if let Some(renamed) = tags::reparent(tag, &old, &new) {
+                    *tag = renamed;
+                }
+            }
+            project.tags.sort();
+            project.tags.dedup();
+            changed += 1;
+        }
+        Ok(changed)
+    }
+
+    /// Remove `tag` and every tag nested below it from all projects.
+    /// Returns the number of projects changed.
+    pub fn delete_tag(&mut self, tag: &str) -> Result<usize, PrjError> {
+        let tag = tags::normalize(tag)?;
+        let mut changed = 0;
+        for project in self.projects.iter_mut().filter(|p| p.has_tag(&tag)) {
+            project.tags.retain(|t| !tags::matches(t, &tag));
+            changed += 1;
+        }
+        Ok(changed)
+    }
+
+    /// Change a project's display name, keeping everything else.
+    ///
+    /// Workspace members named `old/member` are renamed to `new/member`
+    /// unless that name is already taken.
+    pub fn rename(&mut self, query: &str, new_name: &str) -> Result<&Project, PrjError> {
+        let idx = self.resolve_index(query)?;
+        if self.projects[idx].name != new_name && self.name_taken(new_name) {
+            return Err(PrjError::ProjectNameTaken(new_name.to_string()));
+        }
+        let old_name = std::mem::replace(&mut self.projects[idx].name, new_name.to_string());
+        let parent_path = self.projects[idx].path.clone();
+
+        let prefix = format!("{old_name}/");
+        for i in self.positions(|p| p.parent.as_ref() == Some(&parent_path)) {
+            let Some(rest) = self.projects[i].name.strip_prefix(&prefix) else {
+                continue;
+            };
+            let renamed = format!("{new_name}/{rest}");
+            if !self.name_taken(&renamed) {
+                self.projects[i].name = renamed;
+            }
+        }
+        Ok(&self.projects[idx])
+    }
+
+    /// Check that the project matching `query` could be relocated to
+    /// `new_path` before anything is moved on disk: the project resolves
+    /// and no other project is registered at the destination, which need
+    /// not exist yet.
+    pub fn check_relocate(&self, query: &str, new_path: &Path) -> Result<(), PrjError> {
+        let idx = self.resolve_index(query)?;
+        let absolute = std::path::absolute(new_path)?;
+        // Registered paths are canonical; resolve symlinks in whatever
+        // part of the destination already exists.
+        let target = match (absolute.parent(), absolute.file_name()) {
+            (Some(parent), Some(name)) => parent
+                .canonicalize()
+                .map(|p| p.join(name))
+                .unwrap_or(absolute.clone()),
+            _ => absolute.clone(),
+        };
+        let target = target.canonicalize().unwrap_or(target);
+        if self
+            .projects
+            .iter()
+            .enumerate()
+            .any(|(i, p)| i != idx && p.path == target)
+        {
+            return Err(PrjError::ProjectAlreadyExists(target.display().to_string()));
+        }
+        Ok(())
+    }
+
+    /// Point a project at a new directory and re-run detection there.
+    ///
+    /// Name, tags, visit history and `added_at` are kept. The directory must
+    /// already exist at `new_path`; moving it is up to the caller.
+    pub fn relocate(
+        &mut self,
+        query: &str,
+        new_path: &Path,
+        custom: &[CustomBuildSystem],
+    ) -> Result<&Project, PrjError> {
+        let idx = self.resolve_index(query)?;
+        let new_path = new_path
+            .canonicalize()
+            .map_err(|_| PrjError::PathNotFound(new_path.to_path_buf()))?;
+        if !new_path.is_dir() {
+            return Err(PrjError::NotADirectory(new_path));
+        }
+        if let Some(other) = self.projects.iter().find(|p| p.path == new_path)
+            && other.path != self.projects[idx].path
+        {
+            return Err(PrjError::ProjectAlreadyExists(
+                new_path.display().to_string(),
+            ));
+        }
+
+        // Keep workspace members linked, following them if they lived
+        // inside the old directory.
+        let old_path = std::mem::replace(&mut self.projects[idx].path, new_path.clone());
+        for member in self
+            .projects
+            .iter_mut()
+            .filter(|p| p.parent.as_ref() == Some(&old_path))
+        {
+            if let Ok(rel) = member.path.strip_prefix(&old_path) {
+                member.path = new_path.join(rel);
+            }
+            member.parent = Some(new_path.clone());
+        }
+
+        let project = &mut self.projects[idx];
+        project.refresh(custom);
+        Ok(project)
+    }
+
+    /// Record that the project at `path` was selected, for frecency ranking.
+    pub fn record_visit(&mut self, path: &Path) -> Result<(), PrjError> {
         let project = self
-            .find_mut(name)
-            .ok_or_else(|| PrjError::ProjectNotFound(name.to_string()))?;
-        project.tags.retain(|t| !tags.contains(t));
+            .projects
+            .iter_mut()
+            .find(|p| p.path == path)
+            .ok_or_else(|| PrjError::ProjectNotFound(path.display().to_string()))?;
+        project.visit_count = project.visit_count.saturating_add(1);
+        project.last_visited = Some(Utc::now());
         Ok(())
     }
 
@@ -117,11 +646,63 @@ impl ProjectDatabase {
             .into_iter()
             .partition(|p| !p.path.exists());
         self.projects = alive;
+        self.detach_members(&orphaned);
+        orphaned
+    }
+
+    /// Like [`ProjectDatabase::remove_orphaned`], but only for projects
+    /// at or below `root`.
+    pub fn remove_orphaned_under(&mut self, root: &Path) -> Vec<Project> {
+        let (orphaned, alive): (Vec<_>, Vec<_>) = std::mem::take(&mut self.projects)
+            .into_iter()
+            .partition(|p| p.path.starts_with(root) && !p.path.exists());
+        self.projects = alive;
+        self.detach_members(&orphaned);
         orphaned
     }
 
-    /// Register a project at the given path with detection.
-    pub fn register(&mut self, path: &Path, name: Option<&str>) -> Result<&Project, PrjError> {
+    /// Make the members of `removed` workspace roots top-level projects.
+    fn detach_members(&mut self, removed: &[Project]) {
+        for member in self.projects.iter_mut().filter(|p| {
+            p.parent
+                .as_ref()
+                .is_some_and(|parent| removed.iter().any(|r| &r.path == parent))
+        }) {
+            member.parent = None;
+        }
+    }
+
+    /// Register a project at the given path, detecting built-in and `custom`
+    /// build systems.
+    ///
+    /// Fails with [`PrjError::ProjectNameTaken`] if the name is already in use.
+    pub fn register(
+        &mut self,
+        path: &Path,
+        name: Option<&str>,
+        custom: &[CustomBuildSystem],
+    ) -> Result<&Project, PrjError> {
+        self.register_inner(path, name, custom, false)
+    }
+
+    /// Register a project, qualifying its name with parent directories
+    /// (`work/api`, `oss/api`) if the plain name is already in use.
+    pub fn register_disambiguated(
+        &mut self,
+        path: &Path,
+        name: Option<&str>,
+        custom: &[CustomBuildSystem],
+    ) -> Result<&Project, PrjError> {
+        self.register_inner(path, name, custom, true)
+    }
+
+    fn register_inner(
+        &mut self,
+        path: &Path,
+        name: Option<&str>,
+        custom: &[CustomBuildSystem],
+        disambiguate: bool,
+    ) -> Result<&Project, PrjError> {
         let path = path
             .canonicalize()
             .map_err(|_| PrjError::PathNotFound(path.to_path_buf()))?;
@@ -130,13 +711,18 @@ impl ProjectDatabase {
             return Err(PrjError::NotADirectory(path));
         }
 
-        let detection = crate::detect::detect_project(&path);
+        let detection = crate::detect::detect_project(&path, custom);
 
         let name = name.map(|s| s.to_string()).unwrap_or_else(|| {
             path.file_name()
                 .map(|n| n.to_string_lossy().to_string())
                 .unwrap_or_else(|| "unknown".to_string())
         });
+        let name = if disambiguate {
+            disambiguate_name(&name, &path, |n| self.name_taken(n))
+        } else {
+            name
+        };
 
         let project = Project {
             name,
@@ -144,11 +730,154 @@ impl ProjectDatabase {
             vcs: detection.vcs,
             build_systems: detection.build_systems,
             artifact_dirs: detection.artifact_dirs,
+            shared_artifact_dirs: detection.shared_artifact_dirs,
             added_at: Utc::now(),
             tags: Vec::new(),
+            description: None,
+            metadata: BTreeMap::new(),
+            visit_count: 0,
+            last_visited: None,
+            detected_at: Some(Utc::now()),
+            parent: None,
+            toolchains: detection.toolchains,
+            worktree_of: detection.worktree_of,
+            language: detection.language,
         };
 
         self.add(project)?;
         Ok(self.projects.last().expect("just pushed"))
     }
 }
+
+/// Pick a free name for the project at `path`, starting from `name`.
+///
+/// Parent directory names are prepended one at a time (`api` -> `work/api`
+/// -> `home/work/api`); if every qualified form is taken, a numeric suffix
+/// is appended instead.
+pub(crate) fn disambiguate_name(name: &str, path: &Path, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(name) {
+        return name.to_string();
+    }
+    let mut candidate = name.to_string();
+    for parent in path.ancestors().skip(1) {
+        let Some(dir) = parent.file_name() else {
+            break;
+        };
+        candidate = format!("{}/{candidate}", dir.to_string_lossy());
+        if !taken(&candidate) {
+            return candidate;
+        }
+    }
+    (2..)
+        .map(|n| format!("{name}-{n}"))
+        .find(|c| !taken(c))
+        .expect("unbounded suffix search")
+}
+
+/// A [`ProjectDatabase`] loaded under an exclusive lock.
+///
+/// Dereferences to the database. Changes are persisted with
+/// [`LockedDatabase::commit`], which also records them in the undo
+/// [`Journal`], or with [`LockedDatabase::save`] for bookkeeping that should
+/// not be undoable. The lock is released on drop.
+pub struct LockedDatabase {
+    db: ProjectDatabase,
+    /// Projects as of the last load or commit, to diff the next commit against.
+    original: Vec<Project>,
+    /// Projects as currently held by `storage`, so saves only write changes.
+    stored: Vec<Project>,
+    storage: Box<dyn Storage>,
+    history_limit: usize,
+    _lock: FileLock,
+}
+
+impl LockedDatabase {
+    /// Atomically write the database back to storage without journaling.
+    pub fn save(&mut self) -> Result<(), PrjError> {
+        self.storage.write_changes(&self.db, &self.stored)?;
+        self.stored = self.db.projects.clone();
+        Ok(())
+    }
+
+    /// Save the database and journal the changes made since the last commit
+    /// so they can be reverted with [`LockedDatabase::undo`].
+    pub fn commit(
+        &mut self,
+        operation: Operation,
+        summary: impl Into<String>,
+    ) -> Result<(), PrjError> {
+        self.commit_entry(operation, summary.into(), None)
+    }
+
+    /// Like [`LockedDatabase::commit`] for `prj mv --move`: also records the
+    /// directory move, so that undo moves the directory back.
+    pub fn commit_move(
+        &mut self,
+        summary: impl Into<String>,
+        moved: MovedDir,
+    ) -> Result<(), PrjError> {
+        self.commit_entry(Operation::Move, summary.into(), Some(moved))
+    }
+
+    fn commit_entry(
+        &mut self,
+        operation: Operation,
+        summary: String,
+        moved_dir: Option<MovedDir>,
+    ) -> Result<(), PrjError> {
+        self.save()?;
+        let entry = JournalEntry::diff(operation, summary, &self.original, &self.db.projects);
+        self.original = self.db.projects.clone();
+        let Some(mut entry) = entry else {
+            return Ok(());
+        };
+        entry.moved_dir = moved_dir;
+        let journal_path = self.journal_path();
+        let mut journal = Journal::load(&journal_path)?;
+        journal.push(entry, self.history_limit);
+        journal.save(&journal_path)
+    }
+
+    /// Revert and forget the most recent journaled operation, moving a
+    /// directory moved by `prj mv --move` back first. Also returns the
+    /// restored projects that had to be renamed, see
+    /// [`JournalEntry::revert`].
+    pub fn undo(&mut self) -> Result<(JournalEntry, Vec<(String, String)>), PrjError> {
+        let journal_path = self.journal_path();
+        let mut journal = Journal::load(&journal_path)?;
+        let entry = journal.entries.pop().ok_or(PrjError::NothingToUndo)?;
+        if let Some(moved) = &entry.moved_dir {
+            moved.move_back()?;
+        }
+        let renamed = entry.revert(&mut self.db);
+        if let Err(e) = self.save() {
+            if let Some(moved) = &entry.moved_dir {
+                // Keep the directory where the database says it is
+                let _ = std::fs::rename(&moved.from, &moved.to);
+            }
+            return Err(e);
+        }
+        self.original = self.db.projects.clone();
+        journal.save(&journal_path)?;
+        Ok((entry, renamed))
+    }
+
+    /// Location of the undo journal for this database.
+    pub fn journal_path(&self) -> PathBuf {
+        Journal::path_for(self.storage.path())
+    }
+}
+
+impl Deref for LockedDatabase {
+    type Target = ProjectDatabase;
+
+    fn deref(&self) -> &ProjectDatabase {
+        &self.db
+    }
+}
+
+impl DerefMut for LockedDatabase {
+    fn deref_mut(&mut self) -> &mut ProjectDatabase {
+        &mut self.db
+    }
+}
@@ -0,0 +1,165 @@
+//! Discovering projects below a directory.
+//!
+//! The walk honours `.gitignore` files, a dedicated `.prjignore` (same
+//! syntax) and `exclude` globs from the config. Hidden directories are
+//! skipped unless listed in `include_hidden`, artifact directories are
+//! always skipped, and nothing below a detected project root is visited.
+//! Directories are walked in parallel.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{WalkBuilder, WalkState};
+
+use crate::detect::{ARTIFACT_DIR_NAMES, CustomBuildSystem, is_project};
+use crate::error::PrjError;
+
+/// Per-directory ignore file read in addition to `.gitignore`.
+pub const IGNORE_FILE_NAME: &str = ".prjignore";
+
+/// How a directory tree is walked when scanning for projects.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    pub max_depth: usize,
+    /// Gitignore-style globs, relative to the scanned directory, for
+    /// directories to skip (`Library`, `go/pkg/mod`, `**/*.vmwarevm`).
+    pub exclude: Vec<String>,
+    /// Hidden directory names to descend into anyway (`.config`).
+    pub include_hidden: Vec<String>,
+    pub follow_symlinks: bool,
+    /// Honour `.gitignore`, `.ignore` and git's exclude files.
+    pub respect_gitignore: bool,
+}
+
+impl ScanOptions {
+    fn excludes(&self, root: &Path) -> Result<Override, PrjError> {
+        let mut builder = OverrideBuilder::new(root);
+        for pattern in &self.exclude {
+            // Override globs are whitelists; `!` turns them into ignores
+            builder
+                .add(&format!("!{pattern}"))
+                .map_err(|e| PrjError::InvalidScanPattern(pattern.clone(), e.to_string()))?;
+        }
+        builder
+            .build()
+            .map_err(|e| PrjError::InvalidScanPattern(self.exclude.join(", "), e.to_string()))
+    }
+
+    fn walk_builder(&self, root: &Path) -> Result<WalkBuilder, PrjError> {
+        let mut builder = WalkBuilder::new(root);
+        builder
+            .max_depth(Some(self.max_depth))
+            .follow_links(self.follow_symlinks)
+            .hidden(false)
+            .parents(true)
+            .ignore(self.respect_gitignore)
+            .git_ignore(self.respect_gitignore)
+            .git_global(self.respect_gitignore)
+            .git_exclude(self.respect_gitignore)
+            .require_git(false)
+            .add_custom_ignore_filename(IGNORE_FILE_NAME)
+            .overrides(self.excludes(root)?);
+        Ok(builder)
+    }
+}
+
+/// A snapshot of scan progress, passed to the progress callback.
+#[derive(Debug)]
+pub struct ScanProgress<'a> {
+    pub dirs_visited: usize,
+    pub projects_found: usize,
+    /// Directory being visited.
+    pub current: &'a Path,
+}
+
+/// Scan a directory tree for projects, including `custom` build systems.
+///
+/// Directories are walked in parallel and `on_progress` is called from the
+/// worker threads for every directory visited. Every directory is visited
+/// at most once, even when followed symlinks form cycles or point at the
+/// same directory twice. The result is sorted by path.
+pub fn scan_projects(
+    root: &Path,
+    options: &ScanOptions,
+    custom: &[CustomBuildSystem],
+    on_progress: &(dyn Fn(&ScanProgress) + Sync),
+) -> Result<Vec<PathBuf>, PrjError> {
+    let include_hidden = options.include_hidden.clone();
+    let custom_artifacts: Vec<String> = custom
+        .iter()
+        .flat_map(|c| c.artifact_dirs.iter().cloned())
+        .collect();
+    let real_root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let visited = Mutex::new(HashSet::from([real_root]));
+
+    let walker = options
+        .walk_builder(root)?
+        .filter_entry(move |e| {
+            // Always allow the root itself
+            if e.depth() == 0 {
+                return true;
+            }
+            if !e.file_type().is_some_and(|t| t.is_dir()) {
+                return false;
+            }
+            let name = e.file_name().to_string_lossy();
+            if ARTIFACT_DIR_NAMES.contains(&name.as_ref())
+                || custom_artifacts.iter().any(|d| *d == name)
+            {
+                return false;
+            }
+            // Skip hidden directories (VCS directories are checked via is_project)
+            if name.starts_with('.') && !include_hidden.iter().any(|h| *h == name) {
+                return false;
+            }
+            // Visit each real directory once, however many links lead to it
+            let real = e.path().canonicalize().unwrap_or_else(|_| e.path().into());
+            visited.lock().expect("scan lock poisoned").insert(real)
+        })
+        .build_parallel();
+
+    let found = Mutex::new(Vec::new());
+    let dirs_visited = AtomicUsize::new(0);
+    let projects_found = AtomicUsize::new(0);
+
+    walker.run(|| {
+        Box::new(|entry| {
+            let Ok(entry) = entry else {
+                return WalkState::Continue;
+            };
+            if !entry.file_type().is_some_and(|t| t.is_dir()) {
+                return WalkState::Continue;
+            }
+            let path = entry.path();
+            let visited = dirs_visited.fetch_add(1, Ordering::Relaxed) + 1;
+            let is_root = is_project(path, custom);
+            let projects = if is_root {
+                found
+                    .lock()
+                    .expect("scan lock poisoned")
+                    .push(path.to_path_buf());
+                projects_found.fetch_add(1, Ordering::Relaxed) + 1
+            } else {
+                projects_found.load(Ordering::Relaxed)
+            };
+            on_progress(&ScanProgress {
+                dirs_visited: visited,
+                projects_found: projects,
+                current: path,
+            });
+            // Nothing below a project root is scanned
+            if is_root {
+                WalkState::Skip
+            } else {
+                WalkState::Continue
+            }
+        })
+    });
+
+    let mut found = found.into_inner().expect("scan lock poisoned");
+    found.sort();
+    Ok(found)
+}
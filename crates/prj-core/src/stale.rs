@@ -0,0 +1,116 @@
+//! Finding projects nobody has worked on for a while.
+//!
+//! A project's last activity is the latest of its `HEAD` commit, its most
+//! recently modified file and its last visit from the picker. Projects
+//! with none of these count as never active.
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use ignore::WalkBuilder;
+use serde::Serialize;
+
+use crate::project::Project;
+use crate::stats;
+
+/// Tag `prj stale --archive` gives stale projects. Projects carrying it
+/// are left out of the report unless filtering by tag.
+pub const ARCHIVED_TAG: &str = "archived";
+
+/// The signals a project's last activity is taken from.
+#[derive(Debug, Clone, Serialize)]
+pub struct Activity {
+    pub last_commit: Option<DateTime<Utc>>,
+    pub newest_file: Option<DateTime<Utc>>,
+    pub last_visited: Option<DateTime<Utc>>,
+}
+
+impl Activity {
+    /// Gather the signals needed to tell whether `project` was active since
+    /// `cutoff`. The file tree is only walked if neither the last commit
+    /// nor the last visit is that recent, and the walk stops at the first
+    /// file modified since `cutoff`, so `newest_file` is only the newest
+    /// file for projects that turn out to be stale.
+    pub fn for_project(project: &Project, cutoff: DateTime<Utc>) -> Self {
+        let last_commit = stats::last_commit_time(&project.path);
+        let last_visited = project.last_visited;
+        let newest_file = if [last_commit, last_visited]
+            .into_iter()
+            .flatten()
+            .any(|at| at >= cutoff)
+        {
+            None
+        } else {
+            newest_file_mtime(&project.path, &project.artifact_dirs, cutoff)
+        };
+        Self {
+            last_commit,
+            newest_file,
+            last_visited,
+        }
+    }
+
+    /// The most recent of the three, or `None` if there is no signal.
+    pub fn latest(&self) -> Option<DateTime<Utc>> {
+        [self.last_commit, self.newest_file, self.last_visited]
+            .into_iter()
+            .flatten()
+            .max()
+    }
+
+    /// Which signal [`Activity::latest`] came from.
+    pub fn latest_source(&self) -> Option<&'static str> {
+        let latest = self.latest()?;
+        if self.last_commit == Some(latest) {
+            Some("commit")
+        } else if self.newest_file == Some(latest) {
+            Some("file")
+        } else {
+            Some("visit")
+        }
+    }
+}
+
+/// Modification time of the newest file in the project, or of the first
+/// file found that is at least as new as `stop_at`. Honours ignore files and
+/// skips hidden and `artifact_dirs` directories, so builds and VCS metadata
+/// don't count as activity.
+pub fn newest_file_mtime(
+    path: &Path,
+    artifact_dirs: &[String],
+    stop_at: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let artifacts: Vec<std::path::PathBuf> = artifact_dirs.iter().map(|d| path.join(d)).collect();
+    let walker = WalkBuilder::new(path)
+        .require_git(false)
+        .filter_entry(move |e| !artifacts.iter().any(|a| e.path() == a))
+        .build();
+
+    let mut newest: Option<DateTime<Utc>> = None;
+    let mtimes = walker
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+        .filter_map(|e| e.metadata().ok()?.modified().ok())
+        .map(DateTime::<Utc>::from);
+    for mtime in mtimes {
+        newest = newest.max(Some(mtime));
+        if mtime >= stop_at {
+            break;
+        }
+    }
+    newest
+}
+
+/// Projects whose last activity is before `cutoff`, never-active ones
+/// first and then oldest first, each with its [`Activity`].
+pub fn stale_projects(projects: &[Project], cutoff: DateTime<Utc>) -> Vec<(&Project, Activity)> {
+    use rayon::prelude::*;
+
+    let mut stale: Vec<(&Project, Activity)> = projects
+        .par_iter()
+        .map(|p| (p, Activity::for_project(p, cutoff)))
+        .filter(|(_, activity)| activity.latest().is_none_or(|at| at < cutoff))
+        .collect();
+    stale.sort_by_key(|(_, activity)| activity.latest());
+    stale
+}
@@ -1,10 +1,14 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::path::Path;
+use std::process::Command;
 
 use bytesize::ByteSize;
-use serde::Serialize;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
 
+use crate::detect::VcsType;
 use crate::project::Project;
+use crate::stats_cache::{CacheKey, CachedStats, StatsCache};
 
 #[derive(Debug, Serialize)]
 pub struct GitStatus {
@@ -15,9 +19,185 @@ pub struct GitStatus {
     pub untracked: usize,
     pub ahead: usize,
     pub behind: usize,
+    /// Submodules that are uninitialized, out of date or dirty.
+    pub submodules: Vec<SubmoduleStatus>,
+    pub stashes: usize,
+    /// Paths with unresolved merge conflicts.
+    pub conflicts: usize,
+    /// Operation left in progress, if any.
+    pub state: Option<RepoState>,
+    /// Short id of the checked-out commit when `HEAD` is detached.
+    pub detached_at: Option<String>,
+    /// Local branches without an upstream to push to.
+    pub no_upstream: Vec<String>,
 }
 
+/// A multi-step git operation that has been started but not finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepoState {
+    Merge,
+    Revert,
+    CherryPick,
+    Bisect,
+    Rebase,
+    /// `git am` applying a patch series.
+    ApplyMailbox,
+}
+
+impl RepoState {
+    fn from_git(state: git2::RepositoryState) -> Option<Self> {
+        use git2::RepositoryState as S;
+        match state {
+            S::Clean => None,
+            S::Merge => Some(RepoState::Merge),
+            S::Revert | S::RevertSequence => Some(RepoState::Revert),
+            S::CherryPick | S::CherryPickSequence => Some(RepoState::CherryPick),
+            S::Bisect => Some(RepoState::Bisect),
+            S::Rebase | S::RebaseInteractive | S::RebaseMerge => Some(RepoState::Rebase),
+            S::ApplyMailbox | S::ApplyMailboxOrRebase => Some(RepoState::ApplyMailbox),
+        }
+    }
+}
+
+impl std::fmt::Display for RepoState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            RepoState::Merge => "merge",
+            RepoState::Revert => "revert",
+            RepoState::CherryPick => "cherry-pick",
+            RepoState::Bisect => "bisect",
+            RepoState::Rebase => "rebase",
+            RepoState::ApplyMailbox => "am",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Why a submodule needs attention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SubmoduleState {
+    /// Registered but never checked out (`git submodule update --init`).
+    Uninitialized,
+    /// Checked out at a different commit than the superproject records.
+    OutOfDate,
+    /// Has uncommitted changes or untracked files of its own.
+    Dirty,
+}
+
+impl std::fmt::Display for SubmoduleState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            SubmoduleState::Uninitialized => "uninitialized",
+            SubmoduleState::OutOfDate => "out of date",
+            SubmoduleState::Dirty => "dirty",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// A submodule that is not in sync with its superproject.
+#[derive(Debug, Clone, Serialize)]
+pub struct SubmoduleStatus {
+    pub path: String,
+    pub state: SubmoduleState,
+}
+
+/// Working-copy state reported by any [`VcsBackend`].
+///
+/// `branch` holds the Git branch, Mercurial/Jujutsu bookmark, SVN branch
+/// or Fossil branch. Backends without a staging area or a notion of local
+/// commits leave `staged`, `ahead` and `behind` at zero. Stashes,
+/// conflicts, in-progress operations, detached `HEAD` and branches without
+/// an upstream are only reported for Git.
 #[derive(Debug, Serialize)]
+pub struct VcsStatus {
+    pub vcs: VcsType,
+    pub branch: Option<String>,
+    pub is_dirty: bool,
+    pub changed: usize,
+    pub staged: usize,
+    pub untracked: usize,
+    pub ahead: usize,
+    pub behind: usize,
+    /// Git submodules needing attention; empty for other VCSs.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub submodules: Vec<SubmoduleStatus>,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub stashes: usize,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub conflicts: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<RepoState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detached_at: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub no_upstream: Vec<String>,
+}
+
+impl VcsStatus {
+    /// Whether something easy to forget about needs dealing with:
+    /// conflicts, an unfinished operation, a detached `HEAD`, stashed
+    /// changes or branches that were never pushed.
+    pub fn needs_attention(&self) -> bool {
+        self.conflicts > 0
+            || self.state.is_some()
+            || self.detached_at.is_some()
+            || self.stashes > 0
+            || !self.no_upstream.is_empty()
+    }
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+impl From<GitStatus> for VcsStatus {
+    fn from(git: GitStatus) -> Self {
+        VcsStatus {
+            vcs: VcsType::Git,
+            branch: git.branch,
+            is_dirty: git.is_dirty,
+            changed: git.changed,
+            staged: git.staged,
+            untracked: git.untracked,
+            ahead: git.ahead,
+            behind: git.behind,
+            submodules: git.submodules,
+            stashes: git.stashes,
+            conflicts: git.conflicts,
+            state: git.state,
+            detached_at: git.detached_at,
+            no_upstream: git.no_upstream,
+        }
+    }
+}
+
+/// Most commits [`collect_git_activity`] walks per repository, so huge
+/// histories stay fast.
+pub const ACTIVITY_COMMIT_LIMIT: usize = 20_000;
+
+/// Commit history of a git repository's `HEAD`.
+///
+/// When the history is longer than [`ACTIVITY_COMMIT_LIMIT`], `truncated`
+/// is set and `authors`, `first_commit` and `total_commits` only cover
+/// the newest commits walked.
+#[derive(Debug, Clone, Serialize)]
+pub struct GitActivity {
+    pub last_commit: DateTime<Utc>,
+    pub last_author: String,
+    pub commits_7d: usize,
+    pub commits_30d: usize,
+    pub commits_90d: usize,
+    /// Distinct author emails.
+    pub authors: usize,
+    pub first_commit: DateTime<Utc>,
+    pub total_commits: usize,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LangStats {
     pub code: usize,
     pub comments: usize,
@@ -25,7 +205,7 @@ pub struct LangStats {
     pub files: usize,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocStats {
     pub languages: BTreeMap<String, LangStats>,
     pub total_code: usize,
@@ -34,7 +214,7 @@ pub struct LocStats {
     pub total_files: usize,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiskStats {
     pub total_bytes: u64,
     pub artifact_bytes: u64,
@@ -54,9 +234,32 @@ impl DiskStats {
 #[derive(Debug, Serialize)]
 pub struct ProjectStats {
     pub name: String,
-    pub git: Option<GitStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub toolchains: BTreeMap<String, String>,
+    /// Serialized as `git`, the key `prj stats --json` has always used.
+    #[serde(rename = "git")]
+    pub vcs: Option<VcsStatus>,
+    /// Commit history; git repositories only, and only in single-project
+    /// stats, not in the overview.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activity: Option<GitActivity>,
     pub loc: LocStats,
     pub disk: DiskStats,
+    /// When `loc` and `disk` were computed, if they came from the stats
+    /// cache rather than being collected just now.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached_at: Option<DateTime<Utc>>,
+}
+
+impl ProjectStats {
+    /// How old the cached `loc` and `disk` figures are, if cached.
+    pub fn cache_age(&self) -> Option<TimeDelta> {
+        self.cached_at.map(|at| Utc::now() - at)
+    }
 }
 
 /// Aggregated statistics across all registered projects.
@@ -74,10 +277,18 @@ pub struct OverviewStats {
 pub fn collect_git_status(path: &Path) -> Option<GitStatus> {
     let repo = git2::Repository::open(path).ok()?;
 
-    let branch = repo
-        .head()
-        .ok()
+    let head = repo.head().ok();
+    let branch = head
+        .as_ref()
+        .filter(|h| h.is_branch())
         .and_then(|h| h.shorthand().map(|s| s.to_string()));
+    let detached_at = if repo.head_detached().unwrap_or(false) {
+        head.as_ref()
+            .and_then(|h| h.target())
+            .map(|oid| oid.to_string()[..7].to_string())
+    } else {
+        None
+    };
 
     let statuses = repo
         .statuses(Some(
@@ -90,9 +301,14 @@ pub fn collect_git_status(path: &Path) -> Option<GitStatus> {
     let mut changed = 0;
     let mut staged = 0;
     let mut untracked = 0;
+    let mut conflicts = 0;
 
     for entry in statuses.iter() {
         let s = entry.status();
+        if s.intersects(git2::Status::CONFLICTED) {
+            conflicts += 1;
+            continue;
+        }
         if s.intersects(
             git2::Status::INDEX_NEW
                 | git2::Status::INDEX_MODIFIED
@@ -115,7 +331,7 @@ pub fn collect_git_status(path: &Path) -> Option<GitStatus> {
         }
     }
 
-    let is_dirty = changed > 0 || staged > 0 || untracked > 0;
+    let is_dirty = changed > 0 || staged > 0 || untracked > 0 || conflicts > 0;
 
     // ahead/behind
     let (ahead, behind) = (|| -> Option<(usize, usize)> {
@@ -136,9 +352,369 @@ pub fn collect_git_status(path: &Path) -> Option<GitStatus> {
         untracked,
         ahead,
         behind,
+        submodules: collect_submodules(&repo),
+        // Each stash entry is one line in the `refs/stash` reflog
+        stashes: repo.reflog("refs/stash").map(|r| r.len()).unwrap_or(0),
+        conflicts,
+        state: RepoState::from_git(repo.state()),
+        detached_at,
+        no_upstream: branches_without_upstream(&repo),
     })
 }
 
+/// Names of local branches that have no upstream configured. Empty for
+/// repositories without remotes, where nothing has an upstream.
+fn branches_without_upstream(repo: &git2::Repository) -> Vec<String> {
+    if repo.remotes().map(|r| r.is_empty()).unwrap_or(true) {
+        return Vec::new();
+    }
+    let Ok(branches) = repo.branches(Some(git2::BranchType::Local)) else {
+        return Vec::new();
+    };
+    branches
+        .flatten()
+        .filter(|(b, _)| b.upstream().is_err())
+        .filter_map(|(b, _)| b.name().ok().flatten().map(str::to_string))
+        .collect()
+}
+
+/// When the commit `HEAD` points at was made. Much cheaper than
+/// [`collect_git_activity`], for showing in lists.
+pub fn last_commit_time(path: &Path) -> Option<DateTime<Utc>> {
+    let repo = git2::Repository::open(path).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    commit_time(&commit)
+}
+
+/// Walk the history of `HEAD` (newest first, at most
+/// [`ACTIVITY_COMMIT_LIMIT`] commits). `None` if the path isn't a git
+/// repository or has no commits yet.
+pub fn collect_git_activity(path: &Path) -> Option<GitActivity> {
+    let repo = git2::Repository::open(path).ok()?;
+    let head = repo.head().ok()?.peel_to_commit().ok()?;
+    let last_commit = commit_time(&head)?;
+    let last_author = head.author().name().unwrap_or("unknown").to_string();
+
+    let mut walk = repo.revwalk().ok()?;
+    walk.push(head.id()).ok()?;
+    walk.set_sorting(git2::Sort::TIME).ok()?;
+
+    let now = Utc::now();
+    let windows = [7, 30, 90].map(|days| now - TimeDelta::days(days));
+    let mut recent = [0; 3];
+    let mut authors = HashSet::new();
+    let mut first_commit = last_commit;
+    let mut total_commits = 0;
+    let mut truncated = false;
+
+    for oid in walk.flatten() {
+        if total_commits == ACTIVITY_COMMIT_LIMIT {
+            truncated = true;
+            break;
+        }
+        let Ok(commit) = repo.find_commit(oid) else {
+            continue;
+        };
+        total_commits += 1;
+        if let Some(email) = commit.author().email() {
+            authors.insert(email.to_lowercase());
+        }
+        let Some(time) = commit_time(&commit) else {
+            continue;
+        };
+        for (count, since) in recent.iter_mut().zip(&windows) {
+            if time >= *since {
+                *count += 1;
+            }
+        }
+        first_commit = first_commit.min(time);
+    }
+
+    Some(GitActivity {
+        last_commit,
+        last_author,
+        commits_7d: recent[0],
+        commits_30d: recent[1],
+        commits_90d: recent[2],
+        authors: authors.len(),
+        first_commit,
+        total_commits,
+        truncated,
+    })
+}
+
+fn commit_time(commit: &git2::Commit) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(commit.time().seconds(), 0)
+}
+
+/// Submodules of `repo` that are uninitialized, out of date or dirty.
+fn collect_submodules(repo: &git2::Repository) -> Vec<SubmoduleStatus> {
+    let Ok(submodules) = repo.submodules() else {
+        return Vec::new();
+    };
+    submodules
+        .iter()
+        .filter_map(|sm| {
+            let status = repo
+                .submodule_status(sm.name()?, git2::SubmoduleIgnore::None)
+                .ok()?;
+            let state = if status.contains(git2::SubmoduleStatus::WD_UNINITIALIZED) {
+                SubmoduleState::Uninitialized
+            } else if status.contains(git2::SubmoduleStatus::WD_MODIFIED) {
+                SubmoduleState::OutOfDate
+            } else if status.intersects(
+                git2::SubmoduleStatus::WD_INDEX_MODIFIED
+                    | git2::SubmoduleStatus::WD_WD_MODIFIED
+                    | git2::SubmoduleStatus::WD_UNTRACKED,
+            ) {
+                SubmoduleState::Dirty
+            } else {
+                return None;
+            };
+            Some(SubmoduleStatus {
+                path: sm.path().display().to_string(),
+                state,
+            })
+        })
+        .collect()
+}
+
+/// A version control system that can report working-copy status.
+pub trait VcsBackend: Sync {
+    /// Collect status for the working copy rooted at `path`, or `None` if
+    /// it cannot be read (e.g. the VCS binary is not installed).
+    fn status(&self, path: &Path) -> Option<VcsStatus>;
+}
+
+/// Returns the status backend for `vcs`.
+pub fn backend(vcs: VcsType) -> &'static dyn VcsBackend {
+    match vcs {
+        VcsType::Git => &GitBackend,
+        VcsType::Mercurial => &MercurialBackend,
+        VcsType::Jujutsu => &JujutsuBackend,
+        VcsType::Subversion => &SubversionBackend,
+        VcsType::Fossil => &FossilBackend,
+    }
+}
+
+/// Collect working-copy status for a project path using whichever VCS
+/// owns it. Jujutsu takes precedence over a colocated git repository.
+pub fn collect_vcs_status(path: &Path) -> Option<VcsStatus> {
+    let vcs = VcsType::ALL.into_iter().find(|v| v.detected_in(path))?;
+    backend(vcs).status(path)
+}
+
+/// Run a VCS binary in `path` and return its stdout if it succeeded.
+fn run_vcs(program: &str, args: &[&str], path: &Path) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(path)
+        .env("HGPLAIN", "1")
+        .env("LC_ALL", "C")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+fn finish(vcs: VcsType, branch: Option<String>, counts: [usize; 3]) -> VcsStatus {
+    let [changed, staged, untracked] = counts;
+    VcsStatus {
+        vcs,
+        branch,
+        is_dirty: changed > 0 || staged > 0 || untracked > 0,
+        changed,
+        staged,
+        untracked,
+        ahead: 0,
+        behind: 0,
+        submodules: Vec::new(),
+        stashes: 0,
+        conflicts: 0,
+        state: None,
+        detached_at: None,
+        no_upstream: Vec::new(),
+    }
+}
+
+struct GitBackend;
+
+impl VcsBackend for GitBackend {
+    fn status(&self, path: &Path) -> Option<VcsStatus> {
+        collect_git_status(path).map(VcsStatus::from)
+    }
+}
+
+/// Mercurial via the `hg` binary. `ahead` counts draft (unpublished)
+/// ancestors of the working copy; `behind` would need a network round
+/// trip and is left at zero.
+struct MercurialBackend;
+
+impl VcsBackend for MercurialBackend {
+    fn status(&self, path: &Path) -> Option<VcsStatus> {
+        let out = run_vcs("hg", &["status"], path)?;
+        let mut counts = [0; 3];
+        for line in out.lines() {
+            match line.chars().next() {
+                Some('A' | 'R') => counts[1] += 1,
+                Some('M' | '!') => counts[0] += 1,
+                Some('?') => counts[2] += 1,
+                _ => {}
+            }
+        }
+
+        let bookmark = run_vcs("hg", &["log", "-r", ".", "-T", "{activebookmark}"], path)
+            .and_then(|s| non_empty(&s));
+        let branch =
+            bookmark.or_else(|| run_vcs("hg", &["branch"], path).and_then(|s| non_empty(&s)));
+
+        let mut status = finish(VcsType::Mercurial, branch, counts);
+        status.ahead = run_vcs("hg", &["log", "-r", "draft() and ::.", "-T", "x"], path)
+            .map(|s| s.len())
+            .unwrap_or(0);
+        Some(status)
+    }
+}
+
+/// Jujutsu via the `jj` binary. The working-copy commit is always
+/// snapshotted, so every change counts as `changed`. The bookmark is the
+/// nearest one on `::@`, and ahead/behind compare it with its remote.
+struct JujutsuBackend;
+
+impl JujutsuBackend {
+    fn count(path: &Path, revset: &str) -> usize {
+        run_vcs(
+            "jj",
+            &[
+                "log",
+                "--ignore-working-copy",
+                "--no-graph",
+                "-r",
+                revset,
+                "-T",
+                "\"x\"",
+            ],
+            path,
+        )
+        .map(|s| s.len())
+        .unwrap_or(0)
+    }
+}
+
+impl VcsBackend for JujutsuBackend {
+    fn status(&self, path: &Path) -> Option<VcsStatus> {
+        let out = run_vcs(
+            "jj",
+            &["diff", "--ignore-working-copy", "--summary", "-r", "@"],
+            path,
+        )?;
+        let changed = out.lines().filter(|l| !l.trim().is_empty()).count();
+
+        let branch = run_vcs(
+            "jj",
+            &[
+                "log",
+                "--ignore-working-copy",
+                "--no-graph",
+                "-r",
+                "latest(heads(::@ & bookmarks()))",
+                "-T",
+                "local_bookmarks.map(|b| b.name()).join(\" \")",
+            ],
+            path,
+        )
+        .and_then(|s| s.split_whitespace().next().map(str::to_string));
+
+        let mut status = finish(VcsType::Jujutsu, branch, [changed, 0, 0]);
+        if let Some(name) = &status.branch {
+            // Colocated repos also expose `<name>@git`, which is not a real remote.
+            let remote = format!(
+                "(remote_bookmarks(exact:\"{name}\") ~ remote_bookmarks(remote=exact:\"git\"))"
+            );
+            if Self::count(path, &remote) > 0 {
+                status.ahead = Self::count(path, &format!("{remote}..@ ~ empty()"));
+                status.behind = Self::count(path, &format!("@..{remote}"));
+            }
+        }
+        Some(status)
+    }
+}
+
+/// Subversion via the `svn` binary. Scheduled additions, deletions and
+/// replacements count as `staged`. There are no local commits, and
+/// checking the server for `behind` is too slow, so both stay at zero.
+struct SubversionBackend;
+
+impl VcsBackend for SubversionBackend {
+    fn status(&self, path: &Path) -> Option<VcsStatus> {
+        let out = run_vcs("svn", &["status", "--non-interactive"], path)?;
+        let mut counts = [0; 3];
+        for line in out.lines() {
+            match line.chars().next() {
+                Some('A' | 'D' | 'R') => counts[1] += 1,
+                Some('M' | 'C' | '!' | '~') => counts[0] += 1,
+                Some('?') => counts[2] += 1,
+                _ => {}
+            }
+        }
+
+        let branch = run_vcs(
+            "svn",
+            &["info", "--non-interactive", "--show-item", "relative-url"],
+            path,
+        )
+        .and_then(|url| svn_branch(url.trim()));
+
+        Some(finish(VcsType::Subversion, branch, counts))
+    }
+}
+
+/// Derive a branch name from an SVN repository-relative URL such as
+/// `^/trunk` or `^/project/branches/feature/src`.
+fn svn_branch(url: &str) -> Option<String> {
+    let mut parts = url.trim_start_matches("^/").split('/');
+    while let Some(part) = parts.next() {
+        match part {
+            "trunk" => return Some("trunk".to_string()),
+            "branches" | "tags" => return parts.next().map(str::to_string),
+            _ => {}
+        }
+    }
+    non_empty(url)
+}
+
+/// Fossil via the `fossil` binary. Fossil autosyncs by default, so
+/// ahead/behind are left at zero.
+struct FossilBackend;
+
+impl VcsBackend for FossilBackend {
+    fn status(&self, path: &Path) -> Option<VcsStatus> {
+        let out = run_vcs("fossil", &["changes"], path)?;
+        let mut counts = [0; 3];
+        for line in out.lines() {
+            match line.split_whitespace().next() {
+                Some("ADDED" | "DELETED" | "RENAMED") => counts[1] += 1,
+                Some(_) => counts[0] += 1,
+                None => {}
+            }
+        }
+        counts[2] = run_vcs("fossil", &["extras"], path)
+            .map(|s| s.lines().filter(|l| !l.trim().is_empty()).count())
+            .unwrap_or(0);
+
+        let branch = run_vcs("fossil", &["branch", "current"], path).and_then(|s| non_empty(&s));
+
+        Some(finish(VcsType::Fossil, branch, counts))
+    }
+}
+
 /// Collect lines-of-code stats using tokei.
 pub fn collect_loc_stats(path: &Path) -> LocStats {
     let config = tokei::Config {
@@ -201,12 +777,9 @@ pub fn collect_disk_stats(path: &Path, artifact_dirs: &[String]) -> DiskStats {
 
         // Check if this file is inside an artifact directory
         if let Ok(rel) = entry.path().strip_prefix(path)
-            && let Some(first_component) = rel.components().next()
+            && artifact_dirs.iter().any(|a| rel.starts_with(a))
         {
-            let component = first_component.as_os_str().to_string_lossy();
-            if artifact_dirs.iter().any(|a| a == component.as_ref()) {
-                artifact_bytes += size;
-            }
+            artifact_bytes += size;
         }
     }
 
@@ -216,33 +789,119 @@ pub fn collect_disk_stats(path: &Path, artifact_dirs: &[String]) -> DiskStats {
     }
 }
 
-/// Collect full stats for a single project.
+/// Collect full stats for a single project, including commit activity.
 pub fn collect_project_stats(project: &Project) -> ProjectStats {
-    let git = collect_git_status(&project.path);
+    with_activity(project, collect_stats(project))
+}
+
+/// Collect stats for a single project, reusing lines of code and disk
+/// usage from `cache` while the project's [`CacheKey`] still matches.
+/// Returns the stats and the entry to store back into the cache.
+pub fn collect_project_stats_cached(
+    project: &Project,
+    cache: &StatsCache,
+) -> (ProjectStats, CachedStats) {
+    let (stats, entry) = collect_stats_cached(project, cache);
+    (with_activity(project, stats), entry)
+}
+
+/// Commit activity walks the history, which is too slow to repeat for
+/// every project of an overview, so only single-project stats have it.
+fn with_activity(project: &Project, stats: ProjectStats) -> ProjectStats {
+    ProjectStats {
+        activity: collect_git_activity(&project.path),
+        ..stats
+    }
+}
+
+fn collect_stats(project: &Project) -> ProjectStats {
     let loc = collect_loc_stats(&project.path);
     let disk = collect_disk_stats(&project.path, &project.artifact_dirs);
+    project_stats(project, loc, disk, None)
+}
+
+fn collect_stats_cached(project: &Project, cache: &StatsCache) -> (ProjectStats, CachedStats) {
+    let key = CacheKey::for_project(project);
+    let (entry, cached_at) = match cache.get(project, &key) {
+        Some(cached) => (cached.clone(), Some(cached.computed_at)),
+        None => {
+            let entry = CachedStats {
+                key,
+                computed_at: Utc::now(),
+                loc: collect_loc_stats(&project.path),
+                disk: collect_disk_stats(&project.path, &project.artifact_dirs),
+            };
+            (entry, None)
+        }
+    };
+    let stats = project_stats(project, entry.loc.clone(), entry.disk.clone(), cached_at);
+    (stats, entry)
+}
 
+fn project_stats(
+    project: &Project,
+    loc: LocStats,
+    disk: DiskStats,
+    cached_at: Option<DateTime<Utc>>,
+) -> ProjectStats {
     ProjectStats {
         name: project.name.clone(),
-        git,
+        description: project.description.clone(),
+        metadata: project.metadata.clone(),
+        toolchains: project.toolchains.clone(),
+        vcs: collect_vcs_status(&project.path),
+        activity: None,
         loc,
         disk,
+        cached_at,
     }
 }
 
 /// Collect overview stats across all projects (parallelized with rayon).
+///
+/// Workspace members are listed individually but left out of the totals,
+/// since their files are already counted in their workspace root.
 pub fn collect_overview_stats(projects: &[Project]) -> OverviewStats {
     use rayon::prelude::*;
 
-    let project_stats: Vec<ProjectStats> = projects.par_iter().map(collect_project_stats).collect();
+    let project_stats: Vec<ProjectStats> = projects.par_iter().map(collect_stats).collect();
+    overview(projects, project_stats)
+}
+
+/// Like [`collect_overview_stats`], but reusing and updating `cache` as
+/// [`collect_project_stats_cached`] does. Entries for projects not in
+/// `projects` are left alone.
+pub fn collect_overview_stats_cached(
+    projects: &[Project],
+    cache: &mut StatsCache,
+) -> OverviewStats {
+    use rayon::prelude::*;
+
+    let (project_stats, entries): (Vec<ProjectStats>, Vec<CachedStats>) = projects
+        .par_iter()
+        .map(|p| collect_stats_cached(p, cache))
+        .unzip();
+    for (project, entry) in projects.iter().zip(entries) {
+        cache.entries.insert(project.path.clone(), entry);
+    }
+    overview(projects, project_stats)
+}
+
+fn overview(projects: &[Project], project_stats: Vec<ProjectStats>) -> OverviewStats {
+    let top_level: Vec<&ProjectStats> = projects
+        .iter()
+        .zip(&project_stats)
+        .filter(|(p, _)| p.parent.is_none())
+        .map(|(_, s)| s)
+        .collect();
 
     let total_projects = project_stats.len();
-    let total_code_lines: usize = project_stats.iter().map(|s| s.loc.total_code).sum();
-    let total_disk_bytes: u64 = project_stats.iter().map(|s| s.disk.total_bytes).sum();
-    let total_artifact_bytes: u64 = project_stats.iter().map(|s| s.disk.artifact_bytes).sum();
-    let dirty_projects = project_stats
+    let total_code_lines: usize = top_level.iter().map(|s| s.loc.total_code).sum();
+    let total_disk_bytes: u64 = top_level.iter().map(|s| s.disk.total_bytes).sum();
+    let total_artifact_bytes: u64 = top_level.iter().map(|s| s.disk.artifact_bytes).sum();
+    let dirty_projects = top_level
         .iter()
-        .filter(|s| s.git.as_ref().is_some_and(|g| g.is_dirty))
+        .filter(|s| s.vcs.as_ref().is_some_and(|v| v.is_dirty))
         .count();
 
     OverviewStats {
@@ -0,0 +1,173 @@
+//! Persistent cache of the expensive parts of project statistics.
+//!
+//! Lines of code (a full tokei pass) and disk usage (a full walk) are
+//! cached per project under a [`CacheKey`] built from the git `HEAD` oid,
+//! the git index mtime and a fingerprint of every directory's mtime. While
+//! the key matches, the cached figures are reused. VCS status is cheap and
+//! always collected fresh.
+//!
+//! Editing a file in place changes none of these, so the cache can be
+//! stale after such edits until `HEAD`, the index or a directory changes;
+//! `prj stats --refresh` forces recomputation. Artifact directories only
+//! contribute their own mtime, so a rebuild that replaces files deep inside
+//! `target` may also go unnoticed.
+
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::detect::ARTIFACT_DIR_NAMES;
+use crate::error::PrjError;
+use crate::persist;
+use crate::project::Project;
+use crate::stats::{DiskStats, LocStats};
+
+/// What cached statistics were computed from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheKey {
+    /// Commit `HEAD` pointed at, for git repositories.
+    pub head: Option<String>,
+    /// When the git index was last written.
+    pub index_mtime: Option<DateTime<Utc>>,
+    /// Hash of every directory's path and mtime, and of the
+    /// project's artifact directories. Hex, since TOML has no `u64`.
+    pub fingerprint: String,
+}
+
+impl CacheKey {
+    /// Compute the current key for `project`.
+    pub fn for_project(project: &Project) -> Self {
+        let (head, index_mtime) = git2::Repository::open(&project.path)
+            .map(|repo| {
+                let head = repo
+                    .head()
+                    .ok()
+                    .and_then(|h| h.target())
+                    .map(|oid| oid.to_string());
+                let index_mtime = std::fs::metadata(repo.path().join("index"))
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .map(DateTime::<Utc>::from);
+                (head, index_mtime)
+            })
+            .unwrap_or((None, None));
+
+        Self {
+            head,
+            index_mtime,
+            fingerprint: format!("{:016x}", dir_fingerprint(project)),
+        }
+    }
+}
+
+/// Order-independent hash of the mtimes of every directory below the
+/// project (VCS metadata directories excluded), so the walk can run in
+/// whatever order the filesystem returns entries. Artifact directories
+/// are hashed but not descended into: `node_modules` or `target` can hold
+/// more directories than the rest of the project.
+fn dir_fingerprint(project: &Project) -> u64 {
+    let artifacts: Vec<PathBuf> = project
+        .artifact_dirs
+        .iter()
+        .map(|d| project.path.join(d))
+        .collect();
+    let mut combined: u64 = 0;
+    let mut count: u64 = 0;
+    let mut walker = walkdir::WalkDir::new(&project.path)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| {
+            e.file_type().is_dir()
+                && !matches!(
+                    e.file_name().to_str(),
+                    Some(".git" | ".hg" | ".jj" | ".svn")
+                )
+        });
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if entry.depth() > 0
+            && (artifacts.iter().any(|a| entry.path() == a)
+                || ARTIFACT_DIR_NAMES.contains(&entry.file_name().to_string_lossy().as_ref()))
+        {
+            walker.skip_current_dir();
+        }
+        let mtime = entry
+            .metadata()
+            .ok()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let mut hasher = DefaultHasher::new();
+        entry.path().hash(&mut hasher);
+        mtime.hash(&mut hasher);
+        combined = combined.wrapping_add(hasher.finish());
+        count += 1;
+    }
+
+    let mut hasher = DefaultHasher::new();
+    combined.hash(&mut hasher);
+    count.hash(&mut hasher);
+    project.artifact_dirs.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Cached statistics for one project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedStats {
+    pub key: CacheKey,
+    pub computed_at: DateTime<Utc>,
+    pub loc: LocStats,
+    pub disk: DiskStats,
+}
+
+/// Cached statistics for every project, keyed by project path.
+///
+/// Stored as `stats-cache.toml` next to the database. The cache is
+/// disposable: an unreadable file is treated as empty.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StatsCache {
+    #[serde(default)]
+    pub entries: BTreeMap<PathBuf, CachedStats>,
+}
+
+impl StatsCache {
+    /// Cache location for a database stored at `db_path`.
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.with_file_name("stats-cache.toml")
+    }
+
+    /// Load the cache, or return an empty one if it is missing or unreadable.
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Atomically write the cache.
+    pub fn save(&self, path: &Path) -> Result<(), PrjError> {
+        let content = toml::to_string(self).map_err(|e| PrjError::StatsCache(e.to_string()))?;
+        persist::write_atomic(path, content.as_bytes())
+            .map_err(|e| PrjError::StatsCache(e.to_string()))
+    }
+
+    /// Cached statistics for `project` if they were computed under `key`.
+    pub fn get(&self, project: &Project, key: &CacheKey) -> Option<&CachedStats> {
+        self.entries
+            .get(&project.path)
+            .filter(|cached| cached.key == *key)
+    }
+
+    /// Drop entries for projects that are no longer registered.
+    pub fn retain_projects(&mut self, projects: &[Project]) {
+        self.entries
+            .retain(|path, _| projects.iter().any(|p| p.path == *path));
+    }
+}
@@ -0,0 +1,139 @@
+//! Recorded statistics snapshots, for showing trends over time.
+//!
+//! `prj stats --record` appends one [`Snapshot`] per project to
+//! `stats-history.jsonl` next to the database, one JSON object per line.
+//! Recording never rewrites earlier entries, and a line left incomplete by
+//! an interrupted write is skipped when loading instead of losing the rest
+//! of the history.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::PrjError;
+use crate::persist::FileLock;
+use crate::project::Project;
+use crate::stats::ProjectStats;
+
+/// One project's statistics at a point in time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub at: DateTime<Utc>,
+    pub name: String,
+    pub path: PathBuf,
+    pub code_lines: usize,
+    /// Lines of code per language.
+    #[serde(default)]
+    pub languages: BTreeMap<String, usize>,
+    pub disk_bytes: u64,
+    pub artifact_bytes: u64,
+    /// Working-copy state, absent for projects without a VCS.
+    pub dirty: Option<bool>,
+}
+
+impl Snapshot {
+    /// Snapshot `stats` collected for `project`, timestamped `at`.
+    pub fn new(project: &Project, stats: &ProjectStats, at: DateTime<Utc>) -> Self {
+        Self {
+            at,
+            name: project.name.clone(),
+            path: project.path.clone(),
+            code_lines: stats.loc.total_code,
+            languages: stats
+                .loc
+                .languages
+                .iter()
+                .map(|(lang, s)| (lang.clone(), s.code))
+                .collect(),
+            disk_bytes: stats.disk.total_bytes,
+            artifact_bytes: stats.disk.artifact_bytes,
+            dirty: stats.vcs.as_ref().map(|v| v.is_dirty),
+        }
+    }
+}
+
+/// Every recorded snapshot, oldest first.
+#[derive(Debug, Default)]
+pub struct StatsHistory {
+    pub snapshots: Vec<Snapshot>,
+}
+
+impl StatsHistory {
+    /// History location for a database stored at `db_path`.
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.with_file_name("stats-history.jsonl")
+    }
+
+    /// Load the history, or return an empty one if nothing was recorded.
+    /// Lines that don't parse are skipped.
+    pub fn load(path: &Path) -> Result<Self, PrjError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content =
+            std::fs::read_to_string(path).map_err(|e| PrjError::StatsHistory(e.to_string()))?;
+        let mut snapshots: Vec<Snapshot> = content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        snapshots.sort_by_key(|s| s.at);
+        Ok(Self { snapshots })
+    }
+
+    /// Append `snapshots` to the history file, waiting up to `timeout` for
+    /// other `prj` processes recording at the same time.
+    pub fn append(
+        path: &Path,
+        snapshots: Vec<Snapshot>,
+        timeout: Duration,
+    ) -> Result<(), PrjError> {
+        if snapshots.is_empty() {
+            return Ok(());
+        }
+        let _lock = FileLock::acquire(path, timeout)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+
+        // Start on a fresh line if an earlier write was cut off.
+        let mut block = String::new();
+        if file.seek(SeekFrom::End(0))? > 0 {
+            let mut last = [0; 1];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                block.push('\n');
+            }
+        }
+        for snapshot in &snapshots {
+            let line = serde_json::to_string(snapshot)
+                .map_err(|e| PrjError::StatsHistory(e.to_string()))?;
+            block.push_str(&line);
+            block.push('\n');
+        }
+        file.write_all(block.as_bytes())?;
+        Ok(())
+    }
+
+    /// Snapshots taken at or after `since` (all of them if `None`),
+    /// grouped by project path in order of first appearance.
+    pub fn series(&self, since: Option<DateTime<Utc>>) -> Vec<Vec<&Snapshot>> {
+        let mut series: Vec<Vec<&Snapshot>> = Vec::new();
+        for snapshot in &self.snapshots {
+            if since.is_some_and(|since| snapshot.at < since) {
+                continue;
+            }
+            match series.iter_mut().find(|s| s[0].path == snapshot.path) {
+                Some(s) => s.push(snapshot),
+                None => series.push(vec![snapshot]),
+            }
+        }
+        series
+    }
+}
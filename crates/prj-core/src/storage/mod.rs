@@ -0,0 +1,107 @@
+//! Pluggable persistence for the project database.
+//!
+//! Backends only move untyped data in and out; schema migration and locking
+//! are handled uniformly by [`ProjectDatabase`](crate::project::ProjectDatabase).
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod toml_file;
+
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::error::PrjError;
+use crate::migrate;
+use crate::project::{Project, ProjectDatabase};
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStorage;
+pub use toml_file::TomlStorage;
+
+/// Storage backends selectable with `storage` in `config.toml`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// A single `projects.toml` file, rewritten on every save.
+    #[default]
+    Toml,
+    /// A SQLite database updated in a transaction on every save.
+    Sqlite,
+}
+
+impl StorageBackend {
+    /// File name used when `database_path` is not configured.
+    pub fn default_file_name(self) -> &'static str {
+        match self {
+            StorageBackend::Toml => "projects.toml",
+            StorageBackend::Sqlite => "projects.db",
+        }
+    }
+}
+
+impl std::fmt::Display for StorageBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageBackend::Toml => write!(f, "toml"),
+            StorageBackend::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+impl FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "toml" => Ok(StorageBackend::Toml),
+            "sqlite" => Ok(StorageBackend::Sqlite),
+            _ => Err(format!("unknown storage backend: {s} (expected toml or sqlite)")),
+        }
+    }
+}
+
+/// A place the project database can be read from and written to.
+pub trait Storage {
+    /// Location of the stored data; the database lock lives next to it.
+    fn path(&self) -> &Path;
+
+    /// Read the stored database as an untyped table in the `projects.toml`
+    /// layout, or `None` if nothing has been stored yet.
+    fn read_raw(&self) -> Result<Option<toml::Table>, PrjError>;
+
+    /// Replace the stored database with `db`. Readers must never observe a
+    /// partially written database.
+    fn write(&self, db: &ProjectDatabase) -> Result<(), PrjError>;
+
+    /// Like [`Storage::write`], given that storage currently holds the
+    /// projects in `stored`. Backends that can update records in place only
+    /// write the projects that differ; the default rewrites everything.
+    fn write_changes(&self, db: &ProjectDatabase, stored: &[Project]) -> Result<(), PrjError> {
+        let _ = stored;
+        self.write(db)
+    }
+
+    /// Keep a copy of the stored data before it is migrated from `version`.
+    fn backup(&self, version: u32) -> Result<(), PrjError> {
+        migrate::write_backup(self.path(), version)
+    }
+}
+
+/// Open the backend selected in the config.
+pub fn open(config: &Config) -> Result<Box<dyn Storage>, PrjError> {
+    open_backend(config.storage, &config.database_path())
+}
+
+/// Open a specific backend at `path`.
+pub fn open_backend(backend: StorageBackend, path: &Path) -> Result<Box<dyn Storage>, PrjError> {
+    match backend {
+        StorageBackend::Toml => Ok(Box::new(TomlStorage::new(path))),
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite => Ok(Box::new(SqliteStorage::new(path))),
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackend::Sqlite => Err(PrjError::StorageUnavailable(backend)),
+    }
+}
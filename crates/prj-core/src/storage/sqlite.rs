@@ -0,0 +1,410 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Row, Transaction, params};
+use toml::{Table, Value};
+
+use super::Storage;
+use crate::error::PrjError;
+use crate::project::{Project, ProjectDatabase};
+
+/// One row per project. The fields that change on visits, renames and
+/// tagging have their own columns, so those updates touch nothing else.
+/// Everything set at detection time is kept as TOML in `data`, so the same
+/// migrations apply to both backends.
+///
+/// `position` is sparse: removing a project leaves a gap instead of
+/// renumbering the rows after it.
+const SCHEMA: &str = "
+CREATE TABLE meta (
+    key   TEXT PRIMARY KEY,
+    value INTEGER NOT NULL
+);
+CREATE TABLE projects (
+    path         TEXT PRIMARY KEY,
+    name         TEXT NOT NULL,
+    position     INTEGER NOT NULL,
+    data         TEXT NOT NULL,
+    tags         TEXT NOT NULL,
+    visit_count  INTEGER NOT NULL,
+    last_visited TEXT
+);
+";
+
+/// Project fields stored in columns rather than in `data`.
+const COLUMN_FIELDS: &[&str] = &["path", "name", "tags", "visit_count", "last_visited"];
+
+/// Distance between the positions of projects appended at the end.
+const POSITION_GAP: i64 = 1024;
+
+/// Opt-in backend storing projects in a SQLite database.
+///
+/// Saves run in a single transaction. [`Storage::write_changes`] only
+/// writes the rows of projects that changed, and only the columns that
+/// changed when a visit, rename or tag edit is all there is.
+pub struct SqliteStorage {
+    path: PathBuf,
+}
+
+impl SqliteStorage {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Write `db`, diffing against `stored` when the caller knows what
+    /// storage holds, or rewriting every row otherwise.
+    fn write_rows(&self, db: &ProjectDatabase, stored: Option<&[Project]>) -> Result<(), PrjError> {
+        let write_err = |e: rusqlite::Error| PrjError::DatabaseWrite(Box::new(e));
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| PrjError::DatabaseWrite(Box::new(e)))?;
+        }
+        let mut conn = Connection::open(&self.path).map_err(write_err)?;
+        let tx = conn.transaction().map_err(write_err)?;
+
+        // A new file gets its tables with the first write
+        let has_schema: bool = tx
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'projects')",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(write_err)?;
+        if !has_schema {
+            tx.execute_batch(SCHEMA).map_err(write_err)?;
+        }
+
+        tx.execute(
+            "INSERT INTO meta (key, value) VALUES ('schema_version', ?1)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            [db.version],
+        )
+        .map_err(write_err)?;
+
+        let current: HashMap<String, i64> = tx
+            .prepare("SELECT path, position FROM projects")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<rusqlite::Result<_>>()
+            })
+            .map_err(write_err)?;
+
+        let paths: Vec<String> = db
+            .projects
+            .iter()
+            .map(|p| p.path.to_string_lossy().to_string())
+            .collect();
+        let keep: HashSet<&str> = paths.iter().map(String::as_str).collect();
+        for path in current.keys().filter(|p| !keep.contains(p.as_str())) {
+            tx.execute("DELETE FROM projects WHERE path = ?1", [path])
+                .map_err(write_err)?;
+        }
+
+        let old_positions: Vec<Option<i64>> =
+            paths.iter().map(|p| current.get(p).copied()).collect();
+        let positions = assign_positions(&old_positions);
+        let stored: Option<HashMap<&Path, &Project>> =
+            stored.map(|s| s.iter().map(|p| (p.path.as_path(), p)).collect());
+
+        for (i, project) in db.projects.iter().enumerate() {
+            let (path, position) = (&paths[i], positions[i]);
+            let old = match (&stored, old_positions[i]) {
+                (Some(stored), Some(_)) => stored.get(project.path.as_path()).copied(),
+                _ => None,
+            };
+            let Some(old) = old else {
+                upsert_row(&tx, path, position, project)?;
+                continue;
+            };
+            let moved = old_positions[i] != Some(position);
+            if old == project {
+                if moved {
+                    tx.execute(
+                        "UPDATE projects SET position = ?2 WHERE path = ?1",
+                        params![path, position],
+                    )
+                    .map_err(write_err)?;
+                }
+            } else if without_column_fields(old) == without_column_fields(project) {
+                update_columns(&tx, path, position, project)?;
+            } else {
+                upsert_row(&tx, path, position, project)?;
+            }
+        }
+
+        tx.commit().map_err(write_err)
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read_raw(&self) -> Result<Option<Table>, PrjError> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let read_err = |e: rusqlite::Error| PrjError::DatabaseRead(Box::new(e));
+        let conn = Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(read_err)?;
+
+        let version: Option<i64> = conn
+            .query_row(
+                "SELECT value FROM meta WHERE key = 'schema_version'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(read_err)?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT path, name, data, tags, visit_count, last_visited
+                 FROM projects ORDER BY position",
+            )
+            .map_err(read_err)?;
+        let mut rows = stmt.query([]).map_err(read_err)?;
+        let mut projects = Vec::new();
+        while let Some(row) = rows.next().map_err(read_err)? {
+            projects.push(Value::Table(read_row(row)?));
+        }
+
+        let mut raw = Table::new();
+        if let Some(version) = version {
+            raw.insert("version".to_string(), Value::Integer(version));
+        }
+        raw.insert("projects".to_string(), Value::Array(projects));
+        Ok(Some(raw))
+    }
+
+    fn write(&self, db: &ProjectDatabase) -> Result<(), PrjError> {
+        self.write_rows(db, None)
+    }
+
+    fn write_changes(&self, db: &ProjectDatabase, stored: &[Project]) -> Result<(), PrjError> {
+        self.write_rows(db, Some(stored))
+    }
+}
+
+/// Rebuild a project's raw table from its row.
+fn read_row(row: &Row) -> Result<Table, PrjError> {
+    let read_err = |e: rusqlite::Error| PrjError::DatabaseRead(Box::new(e));
+    let path: String = row.get(0).map_err(read_err)?;
+    let name: String = row.get(1).map_err(read_err)?;
+    let data: String = row.get(2).map_err(read_err)?;
+    let tags: String = row.get(3).map_err(read_err)?;
+    let visit_count: i64 = row.get(4).map_err(read_err)?;
+    let last_visited: Option<String> = row.get(5).map_err(read_err)?;
+
+    let mut project = data
+        .parse::<Table>()
+        .map_err(|e| PrjError::DatabaseRead(Box::new(e)))?;
+    let tags: Vec<String> =
+        serde_json::from_str(&tags).map_err(|e| PrjError::DatabaseRead(Box::new(e)))?;
+    project.insert("path".to_string(), Value::String(path));
+    project.insert("name".to_string(), Value::String(name));
+    project.insert(
+        "tags".to_string(),
+        Value::Array(tags.into_iter().map(Value::String).collect()),
+    );
+    project.insert("visit_count".to_string(), Value::Integer(visit_count));
+    if let Some(at) = last_visited {
+        project.insert("last_visited".to_string(), Value::String(at));
+    }
+    Ok(project)
+}
+
+/// Insert or fully rewrite a project's row.
+fn upsert_row(
+    tx: &Transaction,
+    path: &str,
+    position: i64,
+    project: &Project,
+) -> Result<(), PrjError> {
+    let write_err = |e: rusqlite::Error| PrjError::DatabaseWrite(Box::new(e));
+    let mut data = Table::try_from(project).map_err(|e| PrjError::DatabaseWrite(Box::new(e)))?;
+    for field in COLUMN_FIELDS {
+        data.remove(*field);
+    }
+    let data = toml::to_string(&data).map_err(|e| PrjError::DatabaseWrite(Box::new(e)))?;
+    let tags =
+        serde_json::to_string(&project.tags).map_err(|e| PrjError::DatabaseWrite(Box::new(e)))?;
+    tx.execute(
+        "INSERT INTO projects (path, name, position, data, tags, visit_count, last_visited)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(path) DO UPDATE SET
+             name = excluded.name,
+             position = excluded.position,
+             data = excluded.data,
+             tags = excluded.tags,
+             visit_count = excluded.visit_count,
+             last_visited = excluded.last_visited",
+        params![
+            path,
+            project.name,
+            position,
+            data,
+            tags,
+            project.visit_count,
+            project.last_visited.map(|at| at.to_rfc3339()),
+        ],
+    )
+    .map_err(write_err)?;
+    Ok(())
+}
+
+/// Update only the column fields of a project whose `data` is unchanged.
+fn update_columns(
+    tx: &Transaction,
+    path: &str,
+    position: i64,
+    project: &Project,
+) -> Result<(), PrjError> {
+    let write_err = |e: rusqlite::Error| PrjError::DatabaseWrite(Box::new(e));
+    let tags =
+        serde_json::to_string(&project.tags).map_err(|e| PrjError::DatabaseWrite(Box::new(e)))?;
+    let updated = tx
+        .execute(
+            "UPDATE projects
+             SET name = ?2, position = ?3, tags = ?4, visit_count = ?5, last_visited = ?6
+             WHERE path = ?1",
+            params![
+                path,
+                project.name,
+                position,
+                tags,
+                project.visit_count,
+                project.last_visited.map(|at| at.to_rfc3339()),
+            ],
+        )
+        .map_err(write_err)?;
+    if updated == 0 {
+        upsert_row(tx, path, position, project)?;
+    }
+    Ok(())
+}
+
+/// `project` with the fields stored in columns cleared, to tell whether
+/// `data` needs rewriting.
+fn without_column_fields(project: &Project) -> Project {
+    Project {
+        name: String::new(),
+        tags: Vec::new(),
+        visit_count: 0,
+        last_visited: None,
+        ..project.clone()
+    }
+}
+
+/// Strictly increasing positions for projects in their new order, given
+/// each one's stored position (`None` for new projects). The longest run
+/// of stored positions that is still in order is kept; the others are
+/// spaced out in the gaps around them. Only if a gap is too small is
+/// everything renumbered.
+fn assign_positions(old: &[Option<i64>]) -> Vec<i64> {
+    let kept = longest_increasing(old);
+    let mut positions = vec![0; old.len()];
+    let mut prev: Option<i64> = None;
+    let mut i = 0;
+    while i < old.len() {
+        if kept[i] {
+            positions[i] = old[i].unwrap_or_default();
+            prev = Some(positions[i]);
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < old.len() && !kept[i] {
+            i += 1;
+        }
+        let count = (i - start) as i64;
+        let next = (i < old.len()).then(|| old[i].unwrap_or_default());
+        let (low, step) = match (prev, next) {
+            (Some(a), Some(b)) => (a, (b - a) / (count + 1)),
+            (Some(a), None) => (a, POSITION_GAP),
+            (None, Some(b)) => (b.saturating_sub(POSITION_GAP * (count + 1)), POSITION_GAP),
+            (None, None) => (0, POSITION_GAP),
+        };
+        if step == 0 {
+            return (1..=old.len() as i64).map(|n| n * POSITION_GAP).collect();
+        }
+        for (k, slot) in positions[start..i].iter_mut().enumerate() {
+            *slot = low + step * (k as i64 + 1);
+        }
+    }
+    positions
+}
+
+/// Mark a longest strictly increasing subsequence of the present values.
+fn longest_increasing(values: &[Option<i64>]) -> Vec<bool> {
+    // tails[k]: index ending the best increasing run of length k + 1
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev: Vec<Option<usize>> = vec![None; values.len()];
+    for (i, value) in values.iter().enumerate() {
+        let Some(value) = *value else {
+            continue;
+        };
+        let k = tails.partition_point(|&t| values[t].unwrap_or_default() < value);
+        prev[i] = k.checked_sub(1).map(|k| tails[k]);
+        if k == tails.len() {
+            tails.push(i);
+        } else {
+            tails[k] = i;
+        }
+    }
+    let mut kept = vec![false; values.len()];
+    let mut next = tails.last().copied();
+    while let Some(i) = next {
+        kept[i] = true;
+        next = prev[i];
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_after_existing_positions() {
+        assert_eq!(
+            assign_positions(&[Some(1024), Some(2048), None]),
+            [1024, 2048, 3072]
+        );
+    }
+
+    #[test]
+    fn removal_keeps_other_positions() {
+        assert_eq!(
+            assign_positions(&[Some(1024), Some(3072), Some(4096)]),
+            [1024, 3072, 4096]
+        );
+    }
+
+    #[test]
+    fn reorder_moves_only_the_moved_project() {
+        // The last project moved to the front
+        assert_eq!(
+            assign_positions(&[Some(4096), Some(1024), Some(2048), Some(3072)]),
+            [0, 1024, 2048, 3072]
+        );
+    }
+
+    #[test]
+    fn fills_gaps_between_kept_positions() {
+        assert_eq!(
+            assign_positions(&[Some(1024), None, None, Some(2048)]),
+            [1024, 1365, 1706, 2048]
+        );
+    }
+
+    #[test]
+    fn renumbers_when_there_is_no_room() {
+        assert_eq!(
+            assign_positions(&[Some(1), None, Some(2)]),
+            [1024, 2048, 3072]
+        );
+    }
+}
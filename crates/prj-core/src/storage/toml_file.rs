@@ -0,0 +1,44 @@
+use std::path::{Path, PathBuf};
+
+use super::Storage;
+use crate::error::PrjError;
+use crate::persist;
+use crate::project::ProjectDatabase;
+
+/// The default backend: the whole database in one TOML file.
+pub struct TomlStorage {
+    path: PathBuf,
+}
+
+impl TomlStorage {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl Storage for TomlStorage {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read_raw(&self) -> Result<Option<toml::Table>, PrjError> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&self.path)
+            .map_err(|e| PrjError::DatabaseRead(Box::new(e)))?;
+        let raw = content
+            .parse::<toml::Table>()
+            .map_err(|e| PrjError::DatabaseRead(Box::new(e)))?;
+        Ok(Some(raw))
+    }
+
+    fn write(&self, db: &ProjectDatabase) -> Result<(), PrjError> {
+        let content =
+            toml::to_string_pretty(db).map_err(|e| PrjError::DatabaseWrite(Box::new(e)))?;
+        persist::write_atomic(&self.path, content.as_bytes())
+            .map_err(|e| PrjError::DatabaseWrite(Box::new(e)))
+    }
+}
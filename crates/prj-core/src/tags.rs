@@ -0,0 +1,38 @@
+//! Hierarchical tags.
+//!
+//! Tags are `/`-separated paths such as `work/backend`. A tag filter matches
+//! the tag itself and everything below it, so `work` matches `work` and
+//! `work/backend` but not `workshop`.
+
+use crate::error::PrjError;
+
+/// Tag separator.
+pub const SEPARATOR: char = '/';
+
+/// Trim surrounding whitespace and separators, rejecting empty tags and
+/// empty path segments (`a//b`).
+pub fn normalize(tag: &str) -> Result<String, PrjError> {
+    let trimmed = tag.trim().trim_matches(SEPARATOR);
+    if trimmed.is_empty() || trimmed.split(SEPARATOR).any(|s| s.trim().is_empty()) {
+        return Err(PrjError::InvalidTag(tag.to_string()));
+    }
+    Ok(trimmed.to_string())
+}
+
+/// Returns true if `tag` is `filter` or nested below it.
+pub fn matches(tag: &str, filter: &str) -> bool {
+    tag.strip_prefix(filter)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(SEPARATOR))
+}
+
+/// `tag` and every ancestor of it: `a/b/c` -> `a`, `a/b`, `a/b/c`.
+pub fn with_ancestors(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices(SEPARATOR)
+        .map(|(i, _)| &tag[..i])
+        .chain(std::iter::once(tag))
+}
+
+/// Move `tag` from under `old` to under `new`, or `None` if it doesn't match.
+pub fn reparent(tag: &str, old: &str, new: &str) -> Option<String> {
+    matches(tag, old).then(|| format!("{new}{}", &tag[old.len()..]))
+}
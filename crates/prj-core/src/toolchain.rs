@@ -0,0 +1,442 @@
+//! Toolchain and runtime versions a project asks for, and whether they are
+//! installed.
+//!
+//! Requirements are read from `rust-toolchain(.toml)`, `.nvmrc` or
+//! `package.json` `engines.node`, `.python-version`, `go.mod`'s `go`
+//! directive, `.java-version`, and asdf/mise `.tool-versions`. The
+//! dedicated files win over `.tool-versions` for the same tool.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Map of tool name (`rust`, `node`, `python`, `go`, `java`, or any
+/// `.tool-versions` entry) to the required version.
+pub type Toolchains = BTreeMap<String, String>;
+
+/// Read every toolchain requirement declared in `path`.
+pub fn detect_toolchains(path: &Path) -> Toolchains {
+    let mut found = Toolchains::new();
+    let read = |name: &str| std::fs::read_to_string(path.join(name)).ok();
+    let first_line = |name: &str| {
+        read(name).and_then(|s| {
+            s.lines()
+                .map(str::trim)
+                .find(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(str::to_string)
+        })
+    };
+
+    if let Some(content) = read(".tool-versions") {
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut parts = line.split_whitespace();
+            if let (Some(tool), Some(version)) = (parts.next(), parts.next()) {
+                found.insert(canonical_tool(tool).to_string(), version.to_string());
+            }
+        }
+    }
+
+    if let Some(channel) = read("rust-toolchain.toml")
+        .or_else(|| read("rust-toolchain"))
+        .and_then(|s| rust_channel(&s))
+    {
+        found.insert("rust".to_string(), channel);
+    }
+
+    if let Some(version) = first_line(".nvmrc").or_else(|| node_engine(path)) {
+        found.insert("node".to_string(), version);
+    }
+
+    if let Some(version) = first_line(".python-version") {
+        found.insert("python".to_string(), version);
+    }
+
+    if let Some(version) = read("go.mod").and_then(|s| {
+        s.lines()
+            .find_map(|l| l.trim().strip_prefix("go ").map(|v| v.trim().to_string()))
+    }) {
+        found.insert("go".to_string(), version);
+    }
+
+    if let Some(version) = first_line(".java-version") {
+        found.insert("java".to_string(), version);
+    }
+
+    found
+}
+
+/// Each requirement as `tool version`, in tool order.
+pub fn toolchain_list(toolchains: &Toolchains) -> Vec<String> {
+    toolchains
+        .iter()
+        .map(|(tool, version)| format!("{tool} {version}"))
+        .collect()
+}
+
+/// asdf and mise spell some tools differently; use one name for each.
+fn canonical_tool(tool: &str) -> &str {
+    match tool {
+        "nodejs" => "node",
+        "golang" => "go",
+        "rustc" => "rust",
+        other => other,
+    }
+}
+
+/// `rust-toolchain.toml` has a `[toolchain] channel`; the legacy
+/// `rust-toolchain` file is either that TOML or just the channel name.
+fn rust_channel(content: &str) -> Option<String> {
+    if let Ok(table) = content.parse::<toml::Table>()
+        && let Some(channel) = table
+            .get("toolchain")
+            .and_then(|t| t.get("channel"))
+            .and_then(|c| c.as_str())
+    {
+        return Some(channel.to_string());
+    }
+    let line = content.lines().next()?.trim();
+    (!line.is_empty() && !line.contains(['=', '['])).then(|| line.to_string())
+}
+
+fn node_engine(path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(path.join("package.json")).ok()?;
+    let package: serde_json::Value = serde_json::from_str(&content).ok()?;
+    package
+        .get("engines")?
+        .get("node")?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Result of checking one required toolchain.
+#[derive(Debug)]
+pub struct ToolchainCheck {
+    pub tool: String,
+    pub required: String,
+    /// Versions of the tool found locally, empty if it isn't installed.
+    pub installed: Vec<String>,
+    /// Whether an installed version meets the requirement, or `None` if
+    /// that can't be told: a range whose syntax isn't understood, or
+    /// installed versions that aren't numbers (`stable`).
+    pub satisfied: Option<bool>,
+}
+
+/// Checks required toolchains against what is installed, asking each
+/// tool and version manager only once.
+#[derive(Debug, Default)]
+pub struct ToolchainChecker {
+    installed: HashMap<String, Vec<String>>,
+}
+
+impl ToolchainChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check whether `required` of `tool` is installed. Ranges such as
+    /// `>=18`, `^18.2`, `~3.11` or `1.x` are compared against each installed
+    /// version, see [`range_matches`].
+    pub fn check(&mut self, tool: &str, required: &str) -> ToolchainCheck {
+        let installed = self
+            .installed
+            .entry(tool.to_string())
+            .or_insert_with(|| installed_versions(tool))
+            .clone();
+        let satisfied = if installed.is_empty() {
+            Some(false)
+        } else if is_range(required) {
+            let results: Vec<Option<bool>> = installed
+                .iter()
+                .map(|v| range_matches(required, v))
+                .collect();
+            if results.contains(&Some(true)) {
+                Some(true)
+            } else if results.contains(&None) {
+                None
+            } else {
+                Some(false)
+            }
+        } else {
+            Some(installed.iter().any(|v| version_matches(required, v)))
+        };
+        ToolchainCheck {
+            tool: tool.to_string(),
+            required: required.to_string(),
+            installed,
+            satisfied,
+        }
+    }
+}
+
+fn is_range(required: &str) -> bool {
+    required.contains(['<', '>', '=', '^', '~', '*', '|', ' ']) || required.contains(".x")
+}
+
+fn normalize_version(v: &str) -> &str {
+    let v = v.trim();
+    let v = v.strip_prefix("go").unwrap_or(v);
+    v.strip_prefix('v').unwrap_or(v)
+}
+
+/// `1.75` matches `1.75.0` and `1.75.0-x86_64-unknown-linux-gnu`, and
+/// `stable` matches `stable-x86_64-unknown-linux-gnu`.
+fn version_matches(required: &str, installed: &str) -> bool {
+    let required = normalize_version(required);
+    let installed = normalize_version(installed);
+    installed == required
+        || installed
+            .strip_prefix(required)
+            .is_some_and(|rest| rest.starts_with(['.', '-']))
+}
+
+/// A version as `[major, minor, patch]`.
+type Triple = [u64; 3];
+
+/// Numeric components of a version, up to three, stopping at the first
+/// `x`/`*` wildcard. Pre-release and build suffixes (`-rc1`, `+abc`) are
+/// dropped. `None` if a component is anything else.
+fn version_parts(version: &str) -> Option<Vec<u64>> {
+    let version = normalize_version(version);
+    let version = version.split(['-', '+']).next().unwrap_or_default();
+    let mut parts = Vec::new();
+    let mut wildcard = false;
+    for part in version.split('.') {
+        match part {
+            "x" | "X" | "*" => wildcard = true,
+            _ if wildcard => return None,
+            _ => parts.push(part.parse().ok()?),
+        }
+    }
+    parts.truncate(3);
+    Some(parts)
+}
+
+fn pad(parts: &[u64]) -> Triple {
+    let mut triple = [0; 3];
+    triple[..parts.len()].copy_from_slice(parts);
+    triple
+}
+
+/// The smallest version above every version starting with `parts[..=at]`.
+fn bump(parts: &[u64], at: usize) -> Triple {
+    let mut triple = pad(&parts[..=at]);
+    triple[at] += 1;
+    triple
+}
+
+/// Whether `version` meets one comparator such as `>=18`, `^1.2` or `~=3.11`.
+/// Partial versions follow npm: `>1.2` means `>=1.3.0`, `<=1.2` means
+/// `<1.3.0`, and a bare or `=` version matches everything it prefixes.
+fn comparator_matches(comparator: &str, version: Triple) -> Option<bool> {
+    const OPS: &[&str] = &["~=", ">=", "<=", "==", "!=", ">", "<", "=", "^", "~"];
+    let op = OPS
+        .iter()
+        .find(|op| comparator.starts_with(**op))
+        .copied()
+        .unwrap_or("");
+    let parts = version_parts(&comparator[op.len()..])?;
+    let Some(last) = parts.len().checked_sub(1) else {
+        // `*`, `x` or an operator with only a wildcard.
+        return Some(!matches!(op, "<" | ">" | "!="));
+    };
+    let low = pad(&parts);
+    let within = |high: Triple| low <= version && version < high;
+    Some(match op {
+        "" | "=" | "==" => within(bump(&parts, last)),
+        "!=" => !within(bump(&parts, last)),
+        ">=" => version >= low,
+        ">" => version >= bump(&parts, last),
+        "<" => version < low,
+        "<=" => version < bump(&parts, last),
+        "^" => {
+            let first_nonzero = parts.iter().position(|&p| p != 0).unwrap_or(last);
+            within(bump(&parts, first_nonzero))
+        }
+        "~" => within(bump(&parts, last.min(1))),
+        "~=" => within(bump(&parts, last.checked_sub(1)?)),
+        _ => unreachable!(),
+    })
+}
+
+/// Whether `installed` meets a version range: comparators separated by
+/// spaces or commas must all match, and `||` separates alternatives.
+/// `None` if the range or the installed version can't be parsed, e.g. a
+/// hyphen range, `lts/*` or `stable`.
+pub fn range_matches(range: &str, installed: &str) -> Option<bool> {
+    let version = version_parts(installed).filter(|p| !p.is_empty())?;
+    let version = pad(&version);
+
+    let mut any = false;
+    for alternative in range.split("||") {
+        // Join operators written apart from their version (`>= 18`).
+        let mut comparators: Vec<String> = Vec::new();
+        let mut pending = String::new();
+        for token in alternative.split([' ', ',']).filter(|t| !t.is_empty()) {
+            pending.push_str(token);
+            if !token.chars().all(|c| "<>=!^~".contains(c)) {
+                comparators.push(std::mem::take(&mut pending));
+            }
+        }
+        if !pending.is_empty() || comparators.is_empty() {
+            return None;
+        }
+        let mut all = true;
+        for comparator in &comparators {
+            all &= comparator_matches(comparator, version)?;
+        }
+        any |= all;
+    }
+    Some(any)
+}
+
+/// Run a program and return its combined stdout and stderr if it succeeded.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).output().ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let mut text = String::from_utf8_lossy(&out.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&out.stderr));
+    Some(text)
+}
+
+/// Node versions installed by nvm, one per line.
+fn nvm_versions() -> Option<String> {
+    let nvm = std::env::var_os("NVM_DIR")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".nvm")))?;
+    let dir = std::fs::read_dir(nvm.join("versions").join("node")).ok()?;
+    Some(
+        dir.filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+/// Every installed version of `tool` that can be found: the version on
+/// `PATH`, the tool's own version manager, and asdf/mise installs.
+fn installed_versions(tool: &str) -> Vec<String> {
+    let mut versions = Vec::new();
+    let mut push_words = |text: Option<String>, pick: fn(&str) -> Option<&str>| {
+        versions.extend(
+            text.unwrap_or_default()
+                .lines()
+                .filter_map(pick)
+                .map(str::to_string),
+        );
+    };
+
+    match tool {
+        "rust" => {
+            push_words(output("rustup", &["toolchain", "list"]), |l| {
+                // "no installed toolchains" when empty
+                l.split_whitespace()
+                    .next()
+                    .filter(|_| !l.starts_with("no "))
+            });
+            push_words(output("rustc", &["--version"]), |l| {
+                l.split_whitespace().nth(1)
+            });
+        }
+        "node" => {
+            push_words(output("node", &["--version"]), |l| Some(l.trim()));
+            push_words(nvm_versions(), |l| Some(l.trim()));
+        }
+        "python" => {
+            push_words(output("python3", &["--version"]), |l| {
+                l.split_whitespace().nth(1)
+            });
+            push_words(output("python", &["--version"]), |l| {
+                l.split_whitespace().nth(1)
+            });
+            push_words(output("pyenv", &["versions", "--bare"]), |l| Some(l.trim()));
+        }
+        "go" => {
+            push_words(output("go", &["version"]), |l| l.split_whitespace().nth(2));
+        }
+        "java" => {
+            push_words(output("java", &["-version"]), |l| l.split('"').nth(1));
+        }
+        _ => {}
+    }
+
+    // asdf names some plugins differently from the canonical tool name
+    let asdf_name = match tool {
+        "node" => "nodejs",
+        "go" => "golang",
+        other => other,
+    };
+    push_words(output("asdf", &["list", asdf_name]), |l| {
+        Some(l.trim().trim_start_matches('*').trim()).filter(|v| !v.is_empty())
+    });
+    push_words(output("mise", &["ls", "--installed", tool]), |l| {
+        l.split_whitespace().nth(1)
+    });
+
+    versions.retain(|v| !v.is_empty());
+    versions.sort();
+    versions.dedup();
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::range_matches;
+
+    fn matches(range: &str, installed: &str) -> bool {
+        range_matches(range, installed).unwrap_or_else(|| panic!("{range} vs {installed}"))
+    }
+
+    #[test]
+    fn comparators() {
+        assert!(matches(">=20", "v20.11.1"));
+        assert!(!matches(">=20", "v18.19.0"));
+        assert!(matches(">18", "19.0.0"));
+        assert!(!matches(">18", "18.9.0"));
+        assert!(matches("<1.2", "1.1.9"));
+        assert!(!matches("<1.2", "1.2.0"));
+        assert!(matches("<=1.2", "1.2.7"));
+        assert!(!matches("<=1.2", "1.3.0"));
+        assert!(matches("=3.11", "3.11.4"));
+        assert!(matches("!=3.11", "3.12.0"));
+        assert!(!matches("!=3.11", "3.11.1"));
+    }
+
+    #[test]
+    fn caret_tilde_and_wildcards() {
+        assert!(matches("^18", "18.19.0"));
+        assert!(!matches("^18", "20.0.0"));
+        assert!(matches("^0.2.3", "0.2.9"));
+        assert!(!matches("^0.2.3", "0.3.0"));
+        assert!(matches("~3.11", "3.11.4"));
+        assert!(!matches("~3.11", "3.12.0"));
+        assert!(matches("~1.2.3", "1.2.5"));
+        assert!(!matches("~1.2.3", "1.3.0"));
+        assert!(matches("~=3.11", "3.13.0"));
+        assert!(!matches("~=3.11", "4.0.0"));
+        assert!(matches("18.x", "18.2.0"));
+        assert!(!matches("18.x", "19.0.0"));
+        assert!(matches("*", "1.0.0"));
+    }
+
+    #[test]
+    fn compound_ranges() {
+        assert!(matches(">=18 <21", "20.1.0"));
+        assert!(!matches(">=18 <21", "21.0.0"));
+        assert!(matches(">= 3.8, < 4", "3.12.1"));
+        assert!(matches("^16 || ^18", "18.0.0"));
+        assert!(!matches("^16 || ^18", "17.0.0"));
+    }
+
+    #[test]
+    fn unparseable_is_unchecked() {
+        assert_eq!(range_matches("lts/*", "20.0.0"), None);
+        assert_eq!(range_matches("1.0 - 2.0", "1.5.0"), None);
+        assert_eq!(
+            range_matches(">=1.70", "stable-x86_64-unknown-linux-gnu"),
+            None
+        );
+    }
+}
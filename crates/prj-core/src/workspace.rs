@@ -0,0 +1,228 @@
+//! Monorepo workspace definitions.
+//!
+//! A workspace root lists its members in `Cargo.toml` (`[workspace]
+//! members`), `package.json` (`workspaces`, used by npm and yarn),
+//! `pnpm-workspace.yaml` (`packages`) or `go.work` (`use`). Member entries
+//! may be globs such as `crates/*`; a leading `!` excludes matches.
+
+use std::path::{Path, PathBuf};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::detect::{self, ARTIFACT_DIR_NAMES, CustomBuildSystem};
+
+/// How deep a `**` member pattern is followed below the workspace root.
+const MAX_GLOB_DEPTH: usize = 8;
+
+/// Member patterns declared by a workspace root.
+#[derive(Debug, Default)]
+struct Patterns {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl Patterns {
+    fn push(&mut self, pattern: &str) {
+        let (list, pattern) = match pattern.strip_prefix('!') {
+            Some(p) => (&mut self.exclude, p),
+            None => (&mut self.include, pattern),
+        };
+        let pattern = pattern
+            .trim()
+            .trim_start_matches("./")
+            .trim_end_matches('/');
+        if !pattern.is_empty() && pattern != "." && !list.iter().any(|p| p == pattern) {
+            list.push(pattern.to_string());
+        }
+    }
+}
+
+/// Directories of the workspace members declared at `root`, sorted and
+/// without `root` itself. Matches without a project marker (e.g. a stray
+/// directory under `crates/*`) are skipped; `custom` build systems count
+/// as markers too. Returns an empty list if `root` is not a workspace root.
+pub fn workspace_members(root: &Path, custom: &[CustomBuildSystem]) -> Vec<PathBuf> {
+    let mut patterns = Patterns::default();
+    cargo_patterns(root, &mut patterns);
+    npm_patterns(root, &mut patterns);
+    pnpm_patterns(root, &mut patterns);
+    go_work_patterns(root, &mut patterns);
+    expand(root, &patterns, custom)
+}
+
+fn cargo_patterns(root: &Path, patterns: &mut Patterns) {
+    let Ok(content) = std::fs::read_to_string(root.join("Cargo.toml")) else {
+        return;
+    };
+    let Ok(manifest) = content.parse::<toml::Table>() else {
+        return;
+    };
+    let Some(workspace) = manifest.get("workspace").and_then(|w| w.as_table()) else {
+        return;
+    };
+    let strings = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| {
+                a.iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    for member in strings("members") {
+        patterns.push(&member);
+    }
+    for excluded in strings("exclude") {
+        patterns.push(&format!("!{excluded}"));
+    }
+}
+
+/// `workspaces` is either an array of patterns or, for yarn, an object
+/// with a `packages` array.
+fn npm_patterns(root: &Path, patterns: &mut Patterns) {
+    let Ok(content) = std::fs::read_to_string(root.join("package.json")) else {
+        return;
+    };
+    let Ok(package) = serde_json::from_str::<serde_json::Value>(&content) else {
+        return;
+    };
+    let workspaces = match package.get("workspaces") {
+        Some(serde_json::Value::Object(o)) => o.get("packages"),
+        other => other,
+    };
+    if let Some(serde_json::Value::Array(entries)) = workspaces {
+        for entry in entries.iter().filter_map(|e| e.as_str()) {
+            patterns.push(entry);
+        }
+    }
+}
+
+/// Reads the `packages:` list of `pnpm-workspace.yaml`. Only the block
+/// list form pnpm documents is understood, which avoids a YAML dependency.
+fn pnpm_patterns(root: &Path, patterns: &mut Patterns) {
+    let Ok(content) = std::fs::read_to_string(root.join("pnpm-workspace.yaml")) else {
+        return;
+    };
+    let mut in_packages = false;
+    for line in content.lines() {
+        let trimmed = line.split(" #").next().unwrap_or_default().trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = trimmed == "packages:";
+            continue;
+        }
+        if in_packages && let Some(item) = trimmed.strip_prefix('-') {
+            patterns.push(item.trim().trim_matches(['"', '\'']));
+        }
+    }
+}
+
+/// Reads `use ./dir` and `use ( ... )` directives from `go.work`.
+fn go_work_patterns(root: &Path, patterns: &mut Patterns) {
+    let Ok(content) = std::fs::read_to_string(root.join("go.work")) else {
+        return;
+    };
+    let mut in_block = false;
+    for line in content.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        if in_block {
+            if line == ")" {
+                in_block = false;
+            } else if !line.is_empty() {
+                patterns.push(line.trim_matches('"'));
+            }
+        } else if let Some(rest) = line.strip_prefix("use") {
+            let rest = rest.trim();
+            if rest == "(" {
+                in_block = true;
+            } else if !rest.is_empty() {
+                patterns.push(rest.trim_matches('"'));
+            }
+        }
+    }
+}
+
+fn glob_set(patterns: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        if let Ok(glob) = GlobBuilder::new(pattern).literal_separator(true).build() {
+            builder.add(glob);
+        }
+    }
+    builder.build().unwrap_or_else(|_| GlobSet::empty())
+}
+
+fn expand(root: &Path, patterns: &Patterns, custom: &[CustomBuildSystem]) -> Vec<PathBuf> {
+    if patterns.include.is_empty() {
+        return Vec::new();
+    }
+    let include = glob_set(&patterns.include);
+    let exclude = glob_set(&patterns.exclude);
+    let is_glob = |p: &String| p.contains(['*', '?', '[', '{']);
+
+    let mut members: Vec<PathBuf> = patterns
+        .include
+        .iter()
+        .filter(|p| !is_glob(p))
+        .map(|p| root.join(p))
+        .collect();
+
+    let globs: Vec<&String> = patterns.include.iter().filter(|p| is_glob(p)).collect();
+    if !globs.is_empty() {
+        let depth = globs
+            .iter()
+            .map(|p| {
+                if p.contains("**") {
+                    MAX_GLOB_DEPTH
+                } else {
+                    p.split('/').count()
+                }
+            })
+            .max()
+            .unwrap_or(1);
+        let walker = walkdir::WalkDir::new(root)
+            .min_depth(1)
+            .max_depth(depth)
+            .follow_links(false)
+            .into_iter()
+            .filter_entry(|e| {
+                let name = e.file_name().to_string_lossy();
+                e.file_type().is_dir()
+                    && !name.starts_with('.')
+                    && !ARTIFACT_DIR_NAMES.contains(&name.as_ref())
+            });
+        for entry in walker.filter_map(|e| e.ok()) {
+            let Ok(rel) = entry.path().strip_prefix(root) else {
+                continue;
+            };
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            if include.is_match(&rel) {
+                members.push(entry.into_path());
+            }
+        }
+    }
+
+    // Members are only taken from below the root: `../x` or a symlink
+    // out of the tree names an unrelated project, not a child.
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let mut members: Vec<PathBuf> = members
+        .into_iter()
+        .filter_map(|m| m.canonicalize().ok())
+        .collect();
+    members.retain(|m| {
+        let Ok(rel) = m.strip_prefix(&root) else {
+            return false;
+        };
+        m.is_dir()
+            && *m != root
+            && !exclude.is_match(rel.to_string_lossy().replace('\\', "/"))
+            && detect::is_project(m, custom)
+    });
+    members.sort();
+    members.dedup();
+    members
+}
@@ -0,0 +1,116 @@
+//! Git worktrees: resolving a linked worktree back to its main repository,
+//! grouping worktrees under it, and creating new ones.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::PrjError;
+use crate::project::Project;
+
+/// Path of the main working tree when `path` is a linked worktree
+/// (created by `git worktree add`), or `None` for a main working tree,
+/// a submodule, or a directory that isn't a git repository.
+pub fn main_worktree(path: &Path) -> Option<PathBuf> {
+    let repo = git2::Repository::open(path).ok()?;
+    if !repo.is_worktree() {
+        return None;
+    }
+    // The common dir is the main repository's `.git`, or the repository
+    // itself when it is bare.
+    let common = repo.commondir();
+    let common = common
+        .canonicalize()
+        .unwrap_or_else(|_| common.to_path_buf());
+    if common.file_name().is_some_and(|n| n == ".git") {
+        common.parent().map(Path::to_path_buf)
+    } else {
+        Some(common)
+    }
+}
+
+/// Branch checked out in the working tree at `path`.
+pub fn head_branch(path: &Path) -> Option<String> {
+    let repo = git2::Repository::open(path).ok()?;
+    let head = repo.head().ok()?;
+    head.is_branch()
+        .then(|| head.shorthand().map(str::to_string))
+        .flatten()
+}
+
+/// Reorder `projects` so each linked worktree directly follows the
+/// registered project it belongs to. Everything else keeps its order.
+pub fn group_worktrees(projects: &mut Vec<Project>) {
+    let (mut worktrees, rest): (Vec<Project>, Vec<Project>) = std::mem::take(projects)
+        .into_iter()
+        .partition(|p| p.worktree_of.as_ref().is_some_and(|main| *main != p.path));
+    for project in rest {
+        let path = project.path.clone();
+        projects.push(project);
+        let (own, others): (Vec<Project>, Vec<Project>) = worktrees
+            .into_iter()
+            .partition(|w| w.worktree_of.as_ref() == Some(&path));
+        projects.extend(own);
+        worktrees = others;
+    }
+    // Worktrees whose main repository isn't registered
+    projects.extend(worktrees);
+}
+
+/// Where `prj worktree add` puts a new worktree by default: a sibling of
+/// the main working tree named `<repo>-<branch>`, with `/` in the branch
+/// replaced by `-`.
+pub fn default_path(main: &Path, branch: &str) -> PathBuf {
+    let repo = main
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "worktree".to_string());
+    let name = format!("{repo}-{}", branch.replace('/', "-"));
+    main.parent().unwrap_or(main).join(name)
+}
+
+/// Create a worktree of the repository at `repo` checking out `branch` at
+/// `dest`. An existing local or remote branch is checked out; otherwise a
+/// new branch is created from `HEAD`.
+pub fn add(repo: &Path, branch: &str, dest: &Path) -> Result<(), PrjError> {
+    if dest.exists() {
+        return Err(PrjError::DestinationExists(dest.to_path_buf()));
+    }
+    let mut cmd = Command::new("git");
+    cmd.arg("-C").arg(repo).args(["worktree", "add"]);
+    if branch_exists(repo, branch) {
+        cmd.arg(dest).arg(branch);
+    } else {
+        cmd.arg("-b").arg(branch).arg(dest);
+    }
+    let status = cmd
+        .status()
+        .map_err(|e| PrjError::WorktreeFailed(e.to_string()))?;
+    if !status.success() {
+        return Err(PrjError::WorktreeFailed(
+            "git worktree add exited with non-zero status".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `branch` exists locally or on any remote (in which case
+/// `git worktree add` creates a tracking branch for it).
+fn branch_exists(repo: &Path, branch: &str) -> bool {
+    let Ok(repo) = git2::Repository::open(repo) else {
+        return false;
+    };
+    if repo.find_branch(branch, git2::BranchType::Local).is_ok() {
+        return true;
+    }
+    repo.branches(Some(git2::BranchType::Remote))
+        .map(|branches| {
+            branches.flatten().any(|(b, _)| {
+                b.name()
+                    .ok()
+                    .flatten()
+                    .and_then(|n| n.split_once('/'))
+                    .is_some_and(|(_, name)| name == branch)
+            })
+        })
+        .unwrap_or(false)
+}